@@ -0,0 +1,82 @@
+//! Counts the heap allocations made while solving.
+//!
+//! Reads one puzzle per line from the file given as argument, or
+//! `puzzles/hard.txt` by default, and solves each one recursively,
+//! iteratively and with a `Solver` reused for the whole file, printing
+//! how many allocations each one took and the guesses made.
+//!
+//!     cargo run --release --example alloc_bench [FILE]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use sudoku_solver::{Solver, Sudoku};
+
+/// The system allocator, counting every allocation
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// Allocations made by `solve` on a fresh copy of `sudoku`
+fn allocations(sudoku: &Sudoku, solve: impl FnOnce(&mut Sudoku)) -> usize {
+    let mut sudoku = sudoku.clone();
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    solve(&mut sudoku);
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+fn main() {
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "puzzles/hard.txt".to_string());
+    let input = std::fs::read_to_string(&path).expect("could not read the puzzles");
+
+    let mut solver = Solver::new();
+    let mut totals = [0; 4];
+    println!(
+        "{:>4} {:>12} {:>12} {:>12} {:>12}",
+        "#", "recursive", "iterative", "solver", "guesses"
+    );
+    for (i, line) in input.lines().filter(|l| !l.trim().is_empty()).enumerate() {
+        let sudoku = Sudoku::try_from(line).expect("malformed puzzle");
+
+        let counts = [
+            allocations(&sudoku, |s| {
+                s.solve();
+            }),
+            allocations(&sudoku, |s| {
+                s.solve_iterative();
+            }),
+            allocations(&sudoku, |s| {
+                solver.solve_into(s);
+            }),
+            sudoku.backtrack_count(),
+        ];
+        println!(
+            "{:>4} {:>12} {:>12} {:>12} {:>12}",
+            i, counts[0], counts[1], counts[2], counts[3]
+        );
+        for (total, count) in totals.iter_mut().zip(counts.iter()) {
+            *total += count;
+        }
+    }
+    println!(
+        "{:>4} {:>12} {:>12} {:>12} {:>12}",
+        "all", totals[0], totals[1], totals[2], totals[3]
+    );
+}
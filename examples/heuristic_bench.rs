@@ -0,0 +1,63 @@
+//! Compares picking the most constrained cell to guess on with picking
+//! the first empty one.
+//!
+//! Reads one puzzle per line from the file given as argument, or
+//! `puzzles/hard.txt` by default, and solves each one with both
+//! heuristics, several times, printing the guesses and the best time
+//! of each.
+//!
+//!     cargo run --release --example heuristic_bench [FILE]
+
+use std::convert::TryFrom;
+use std::time::{Duration, Instant};
+
+use sudoku_solver::{Heuristic, Sudoku};
+
+const ROUNDS: usize = 10;
+
+/// Guesses and best time out of `ROUNDS` runs on a fresh copy
+fn best_of(sudoku: &Sudoku, heuristic: Heuristic) -> (usize, Duration) {
+    let mut best = Duration::from_secs(u64::MAX);
+    let mut guesses = 0;
+    for _ in 0..ROUNDS {
+        let mut solution = sudoku.clone();
+        let t0 = Instant::now();
+        let stats = solution
+            .solve_with_heuristic(heuristic)
+            .expect("unsolvable puzzle");
+        best = best.min(t0.elapsed());
+        guesses = stats.guesses;
+    }
+    (guesses, best)
+}
+
+fn main() {
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "puzzles/hard.txt".to_string());
+    let input = std::fs::read_to_string(&path).expect("could not read the puzzles");
+
+    let mut total = [(0, Duration::default()); 2];
+    println!(
+        "{:>4} {:>12} {:>12} {:>12} {:>12}",
+        "#", "mrv nodes", "mrv time", "first nodes", "first time"
+    );
+    for (i, line) in input.lines().filter(|l| !l.trim().is_empty()).enumerate() {
+        let sudoku = Sudoku::try_from(line).expect("malformed puzzle");
+
+        let mrv = best_of(&sudoku, Heuristic::MostConstrained);
+        let first = best_of(&sudoku, Heuristic::FirstEmpty);
+        println!(
+            "{:>4} {:>12} {:>12?} {:>12} {:>12?}",
+            i, mrv.0, mrv.1, first.0, first.1
+        );
+        for (total, (guesses, time)) in total.iter_mut().zip([mrv, first].iter()) {
+            total.0 += guesses;
+            total.1 += *time;
+        }
+    }
+    println!(
+        "{:>4} {:>12} {:>12?} {:>12} {:>12?}",
+        "all", total[0].0, total[0].1, total[1].0, total[1].1
+    );
+}
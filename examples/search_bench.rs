@@ -0,0 +1,52 @@
+//! Compares the recursive and the iterative search.
+//!
+//! Reads one puzzle per line from the file given as argument, or
+//! `puzzles/hard.txt` by default, and solves each one with both
+//! strategies, several times, printing the best time of each.
+//!
+//!     cargo run --release --example search_bench [FILE]
+
+use std::convert::TryFrom;
+use std::time::{Duration, Instant};
+
+use sudoku_solver::Sudoku;
+
+const ROUNDS: usize = 10;
+
+/// Best time out of `ROUNDS` runs of `solve` on a fresh copy
+fn best_of(sudoku: &Sudoku, solve: impl Fn(&mut Sudoku) -> bool) -> (Duration, Sudoku) {
+    let mut best = Duration::from_secs(u64::MAX);
+    let mut solution = sudoku.clone();
+    for _ in 0..ROUNDS {
+        solution = sudoku.clone();
+        let t0 = Instant::now();
+        assert!(solve(&mut solution));
+        best = best.min(t0.elapsed());
+    }
+    (best, solution)
+}
+
+fn main() {
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "puzzles/hard.txt".to_string());
+    let input = std::fs::read_to_string(&path).expect("could not read the puzzles");
+
+    let (mut total_recursive, mut total_iterative) = (Duration::default(), Duration::default());
+    println!("{:>4} {:>12} {:>12}", "#", "recursive", "iterative");
+    for (i, line) in input.lines().filter(|l| !l.trim().is_empty()).enumerate() {
+        let sudoku = Sudoku::try_from(line).expect("malformed puzzle");
+
+        let (recursive, expected) = best_of(&sudoku, Sudoku::solve);
+        let (iterative, found) = best_of(&sudoku, Sudoku::solve_iterative);
+        assert_eq!(found, expected, "different solutions for puzzle {}", i);
+
+        println!("{:>4} {:>12?} {:>12?}", i, recursive, iterative);
+        total_recursive += recursive;
+        total_iterative += iterative;
+    }
+    println!(
+        "{:>4} {:>12?} {:>12?}",
+        "all", total_recursive, total_iterative
+    );
+}
@@ -0,0 +1,45 @@
+/*
+ * C API of sudoku_solver, see src/capi.rs for the details.
+ *
+ * Build the library with
+ *
+ *     cargo rustc --release --lib --features capi --crate-type cdylib
+ */
+#ifndef SUDOKU_SOLVER_H
+#define SUDOKU_SOLVER_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Bytes needed to hold a solution: 81 digits and a NUL */
+#define SUDOKU_LINE_LEN 82
+
+#define SUDOKU_SOLVED 0
+#define SUDOKU_UNSOLVABLE 1
+#define SUDOKU_PARSE_ERROR 2
+#define SUDOKU_BUFFER_TOO_SMALL 3
+
+/*
+ * Solves the NUL terminated puzzle in `input` (81 digits or dots, a 0
+ * or a dot being an empty cell, anything else is ignored) and writes
+ * the solution to `out` as a NUL terminated line of 81 digits.
+ * `out_len` must be at least SUDOKU_LINE_LEN.
+ *
+ * Returns one of the status codes above.
+ */
+int sudoku_solve(const char *input, char *out, size_t out_len);
+
+/*
+ * Returns 1 if the puzzle in `input` has exactly one solution, 0 if it
+ * has none or several, and SUDOKU_PARSE_ERROR if it can't be read.
+ */
+int sudoku_is_unique(const char *input);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,661 @@
+//! Boards other than the classic 9x9: of any size up to 32x32 and with
+//! rectangular boxes, or with extra constraints like the windows of a
+//! hypersudoku, inequalities between cells or cells that need an even
+//! or odd digit.
+//!
+//! They are solved the same way as a `Sudoku`, with constraint
+//! propagation and backtracking, but the units are built when the
+//! board is created instead of being computed from the cell index.
+
+use std::fmt;
+
+use crate::{Possible, SudokuError};
+
+/// One bit per digit, so boards can't be bigger than this
+const MAX_SIZE: u8 = 32;
+
+/// Which cells of a board see each other.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Geometry {
+    /// Digits go from 1 to `size`
+    size: u8,
+    /// Groups of `size` cells that hold every digit exactly once
+    units: Vec<Vec<usize>>,
+    /// The index in `units` of every unit a cell belongs to
+    cell_units: Vec<Vec<usize>>,
+    /// The cells that can't hold the same digit as a cell
+    peers: Vec<Vec<usize>>,
+    /// For every cell, the pairs of cells it is part of where the
+    /// first one holds a smaller digit
+    less_than: Vec<Vec<(usize, usize)>>,
+    /// Cells that can only hold even, or odd, digits
+    parity: Vec<(usize, Parity)>,
+}
+
+impl Geometry {
+    /// `cells` cells holding digits from 1 to `size`, and no units yet
+    fn new(size: u8, cells: usize) -> Self {
+        Geometry {
+            size,
+            units: Vec::new(),
+            cell_units: vec![Vec::new(); cells],
+            peers: vec![Vec::new(); cells],
+            less_than: vec![Vec::new(); cells],
+            parity: Vec::new(),
+        }
+    }
+
+    /// Rows, columns and boxes of a board with boxes `box_w` cells
+    /// wide and `box_h` cells tall
+    fn rectangular(box_w: u8, box_h: u8) -> Self {
+        let size = box_w as usize * box_h as usize;
+        let mut geometry = Geometry::new(size as u8, size * size);
+        for i in 0..size {
+            geometry.add_unit((0..size).map(|j| i * size + j).collect());
+            geometry.add_unit((0..size).map(|j| j * size + i).collect());
+
+            // The boxes are numbered left to right, top to bottom
+            let boxes_per_row = size / box_w as usize;
+            let corner =
+                i / boxes_per_row * box_h as usize * size + i % boxes_per_row * box_w as usize;
+            geometry.add_unit(
+                (0..size)
+                    .map(|j| corner + j / box_w as usize * size + j % box_w as usize)
+                    .collect(),
+            );
+        }
+        geometry
+    }
+
+    /// The classic 9x9 board plus four more 3x3 boxes, the windows,
+    /// with their top left corners at rows and columns 1 and 5
+    fn hyper() -> Self {
+        let mut geometry = Geometry::rectangular(3, 3);
+        for &corner in [10, 14, 46, 50].iter() {
+            geometry.add_unit((0..9).map(|j| corner + j / 3 * 9 + j % 3).collect());
+        }
+        geometry
+    }
+
+    /// Cells a knight's move apart see each other, on a square board
+    /// of `size` rows
+    fn add_knight_moves(&mut self) {
+        let size = self.size as isize;
+        let moves = [(1, 2), (2, 1), (2, -1), (1, -2)];
+        for cell in 0..self.cells() {
+            let (r, c) = ((cell as isize) / size, (cell as isize) % size);
+            for &(dr, dc) in moves.iter() {
+                let (r, c) = (r + dr, c + dc);
+                if (0..size).contains(&r) && (0..size).contains(&c) {
+                    self.add_peers(cell, (r * size + c) as usize);
+                }
+            }
+        }
+    }
+
+    fn add_peers(&mut self, a: usize, b: usize) {
+        if !self.peers[a].contains(&b) {
+            self.peers[a].push(b);
+            self.peers[b].push(a);
+        }
+    }
+
+    fn add_unit(&mut self, unit: Vec<usize>) {
+        debug_assert_eq!(unit.len(), self.size as usize);
+        for &a in unit.iter() {
+            self.cell_units[a].push(self.units.len());
+            for &b in unit.iter().filter(|&&b| b != a) {
+                if !self.peers[a].contains(&b) {
+                    self.peers[a].push(b);
+                }
+            }
+        }
+        self.units.push(unit);
+    }
+
+    fn cells(&self) -> usize {
+        self.peers.len()
+    }
+}
+
+/// The possible digits of every cell of a board, the counterpart of
+/// `Values` for a `Geometry`.
+#[derive(Clone, Debug)]
+struct Candidates(Vec<Possible<u32>>);
+
+impl Candidates {
+    /// Returns `None` if the grid is already contradictory
+    fn from_grid(geometry: &Geometry, grid: &[u8]) -> Option<Self> {
+        let mut candidates = Candidates(vec![Possible::full(geometry.size); geometry.cells()]);
+        for (cell, pairs) in geometry.less_than.iter().enumerate() {
+            for &(a, b) in pairs.iter().filter(|&&(a, _)| a == cell) {
+                candidates = candidates.prune(geometry, a, b)?;
+            }
+        }
+        for &(cell, parity) in geometry.parity.iter() {
+            for d in candidates.0[cell].values().filter(|&d| !parity.allows(d)) {
+                candidates = candidates.eliminate(geometry, d, cell)?;
+            }
+        }
+        for (i, &v) in grid.iter().enumerate().filter(|(_, &v)| v != 0) {
+            candidates = candidates.assign(geometry, v, i)?;
+        }
+        Some(candidates)
+    }
+
+    fn search(self, geometry: &Geometry) -> Option<Self> {
+        if self.0.iter().all(|p| p.len() == 1) {
+            return Some(self);
+        }
+
+        let cell = self.most_constrained();
+        self.0[cell]
+            .values()
+            .filter_map(|d| self.clone().assign(geometry, d, cell)?.search(geometry))
+            .next()
+    }
+
+    /// Counts solutions, stopping once `limit` are found
+    fn count_solutions(self, geometry: &Geometry, limit: usize) -> usize {
+        if self.0.iter().all(|p| p.len() == 1) {
+            return 1;
+        }
+
+        let cell = self.most_constrained();
+        let mut count = 0;
+        for d in self.0[cell].values() {
+            if count >= limit {
+                break;
+            }
+            if let Some(candidates) = self.clone().assign(geometry, d, cell) {
+                count += candidates.count_solutions(geometry, limit - count);
+            }
+        }
+        count
+    }
+
+    /// The cell with fewer possibilities left, but more than one.
+    /// Only called when there is such a cell.
+    fn most_constrained(&self) -> usize {
+        let (_, cell) = self
+            .0
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.len() > 1)
+            .map(|(i, p)| (p.len(), i))
+            .min()
+            .unwrap();
+        cell
+    }
+
+    fn assign(mut self, geometry: &Geometry, digit: u8, cell: usize) -> Option<Self> {
+        for other_digit in self.0[cell].values().filter(|&d| d != digit) {
+            self = self.eliminate(geometry, other_digit, cell)?;
+        }
+        Some(self)
+    }
+
+    fn eliminate(mut self, geometry: &Geometry, digit: u8, cell: usize) -> Option<Self> {
+        if !self.0[cell].contains(digit) {
+            return Some(self);
+        }
+
+        let possibles = self.0[cell].remove(digit);
+        self.0[cell] = possibles;
+
+        match possibles.len() {
+            0 => return None,
+            1 => {
+                let d = possibles.n();
+                for &peer in geometry.peers[cell].iter() {
+                    self = self.eliminate(geometry, d, peer)?;
+                }
+            }
+            _ => {}
+        }
+
+        for &(a, b) in geometry.less_than[cell].iter() {
+            self = self.prune(geometry, a, b)?;
+        }
+
+        // A unit with a single place left for the digit gets it there
+        for &unit in geometry.cell_units[cell].iter() {
+            let mut places_for_d = geometry.units[unit]
+                .iter()
+                .filter(|&&p| self.0[p].contains(digit));
+
+            match (places_for_d.next(), places_for_d.next()) {
+                (None, _) => return None,
+                (Some(&place), None) => {
+                    self = self.assign(geometry, digit, place)?;
+                }
+                _ => {}
+            }
+        }
+
+        Some(self)
+    }
+
+    /// With `a` smaller than `b`, `b` can't hold anything up to the
+    /// smallest candidate of `a`, and `a` anything from the biggest of
+    /// `b` up
+    fn prune(mut self, geometry: &Geometry, a: usize, b: usize) -> Option<Self> {
+        let smallest = self.0[a].n();
+        for d in self.0[b].values().filter(|&d| d <= smallest) {
+            self = self.eliminate(geometry, d, b)?;
+        }
+        let biggest = self.0[b].values().last().unwrap_or(0);
+        for d in self.0[a].values().filter(|&d| d >= biggest) {
+            self = self.eliminate(geometry, d, a)?;
+        }
+        Some(self)
+    }
+}
+
+/// Extra rules a `Board` can follow, on top of its units.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SudokuVariant {
+    /// No two cells a knight's move apart hold the same digit
+    AntiKnight,
+}
+
+/// Whether a cell holds an even or an odd digit, see
+/// `Board::with_parity_constraint`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Parity {
+    Even,
+    Odd,
+}
+
+impl Parity {
+    fn allows(self, digit: u8) -> bool {
+        (digit % 2 == 0) == (self == Parity::Even)
+    }
+}
+
+/// A sudoku of any size, with `0` for the empty cells.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Board {
+    geometry: Geometry,
+    box_w: u8,
+    box_h: u8,
+    cells: Vec<u8>,
+}
+
+impl Board {
+    /// A board made of rectangular boxes `box_w` cells wide and
+    /// `box_h` cells tall, holding digits from 1 to `box_w * box_h`.
+    /// `grid` has the cells row by row, and must have exactly that
+    /// many rows and columns.
+    pub fn rectangular(grid: Vec<u8>, box_w: u8, box_h: u8) -> Result<Self, SudokuError> {
+        let size = box_w as usize * box_h as usize;
+        if size == 0 || size > MAX_SIZE as usize || grid.len() != size * size {
+            return Err(SudokuError::InvalidShape {
+                cells: grid.len(),
+                box_w,
+                box_h,
+            });
+        }
+        Board::with_geometry(Geometry::rectangular(box_w, box_h), grid, box_w, box_h)
+    }
+
+    /// See `Sudoku::hyper`
+    pub(crate) fn hyper(grid: Vec<u8>) -> Result<Self, SudokuError> {
+        if grid.len() != 81 {
+            return Err(SudokuError::InvalidShape {
+                cells: grid.len(),
+                box_w: 3,
+                box_h: 3,
+            });
+        }
+        Board::with_geometry(Geometry::hyper(), grid, 3, 3)
+    }
+
+    fn with_geometry(
+        geometry: Geometry,
+        cells: Vec<u8>,
+        box_w: u8,
+        box_h: u8,
+    ) -> Result<Self, SudokuError> {
+        match cells.iter().position(|&d| d > geometry.size) {
+            Some(cell) => Err(SudokuError::InvalidDigit {
+                cell,
+                digit: cells[cell],
+            }),
+            None => Ok(Board {
+                geometry,
+                box_w,
+                box_h,
+                cells,
+            }),
+        }
+    }
+
+    /// Adds the rules of `variant` to the board.
+    pub fn with_variant(mut self, variant: SudokuVariant) -> Self {
+        match variant {
+            SudokuVariant::AntiKnight => self.geometry.add_knight_moves(),
+        }
+        self
+    }
+
+    /// Adds constraints between pairs of cells, indices row by row,
+    /// where the first cell must hold a smaller digit than the second
+    /// one, like in a futoshiki.
+    ///
+    /// Returns `InvalidCell` if a cell is outside the board.
+    pub fn with_inequalities(mut self, inequalities: Vec<(u8, u8)>) -> Result<Self, SudokuError> {
+        let cells = self.geometry.cells();
+        for (a, b) in inequalities {
+            let (a, b) = (a as usize, b as usize);
+            if let Some(&cell) = [a, b].iter().find(|&&cell| cell >= cells) {
+                return Err(SudokuError::InvalidCell { cell });
+            }
+            self.geometry.less_than[a].push((a, b));
+            self.geometry.less_than[b].push((a, b));
+        }
+        Ok(self)
+    }
+
+    /// Only lets digits of `parity` go in `cell`, index row by row,
+    /// like the shaded cells of an even-odd sudoku.
+    ///
+    /// Returns `InvalidCell` if the cell is outside the board.
+    pub fn with_parity_constraint(
+        mut self,
+        cell: usize,
+        parity: Parity,
+    ) -> Result<Self, SudokuError> {
+        if cell >= self.geometry.cells() {
+            return Err(SudokuError::InvalidCell { cell });
+        }
+        self.geometry.parity.push((cell, parity));
+        Ok(self)
+    }
+
+    /// How many rows, columns and digits the board has
+    pub fn size(&self) -> u8 {
+        self.geometry.size
+    }
+
+    /// The cells row by row, `0` when empty
+    pub fn cells(&self) -> &[u8] {
+        &self.cells
+    }
+
+    /// Whether the board has one solution and only one.
+    pub fn is_unique(&self) -> bool {
+        Candidates::from_grid(&self.geometry, &self.cells)
+            .map_or(0, |c| c.count_solutions(&self.geometry, 2))
+            == 1
+    }
+
+    /// Solves the board in place.  Returns false if there is no
+    /// solution, leaving the board untouched.
+    pub fn solve(&mut self) -> bool {
+        let solution = Candidates::from_grid(&self.geometry, &self.cells)
+            .and_then(|candidates| candidates.search(&self.geometry));
+        match solution {
+            Some(candidates) => {
+                for (cell, p) in self.cells.iter_mut().zip(candidates.0) {
+                    *cell = p.n();
+                }
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Same layout as a `Sudoku`, digits over 9 are letters
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let size = self.size() as usize;
+        let (box_w, box_h) = (self.box_w as usize, self.box_h as usize);
+        let line = ("+".to_string() + &"-".repeat(box_w * 2)).repeat(size / box_w) + "+\n";
+        let mut buffer = String::new();
+        for (i, &n) in self.cells.iter().enumerate() {
+            if i != 0 && i % size == 0 {
+                buffer.push_str("|\n");
+            }
+
+            if i % (size * box_h) == 0 {
+                buffer.push_str(&line);
+            }
+
+            if i % box_w == 0 {
+                buffer.push('|');
+            }
+
+            if n != 0 {
+                let c = std::char::from_digit(n as u32, 36).unwrap_or('?');
+                buffer.push(c.to_ascii_uppercase());
+                buffer.push(' ');
+            } else {
+                buffer.push_str(". ");
+            }
+        }
+        buffer.push_str("|\n");
+        buffer.push_str(&line);
+        write!(f, "{}", buffer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Sudoku;
+    use std::convert::TryFrom;
+
+    // 2 rows by 3 columns boxes
+    const SIX: [u8; 36] = [
+        1, 0, 0, 4, 0, 0, //
+        0, 0, 6, 0, 2, 0, //
+        0, 3, 0, 0, 0, 4, //
+        5, 0, 0, 0, 3, 0, //
+        0, 1, 0, 6, 4, 0, //
+        6, 0, 5, 0, 0, 2, //
+    ];
+
+    #[test]
+    fn test_rectangular() {
+        let mut board = Board::rectangular(SIX.to_vec(), 3, 2).unwrap();
+        assert_eq!(board.size(), 6);
+        assert!(board.solve());
+        assert_eq!(
+            board.cells(),
+            &[
+                1, 2, 3, 4, 5, 6, //
+                4, 5, 6, 1, 2, 3, //
+                2, 3, 1, 5, 6, 4, //
+                5, 6, 4, 2, 3, 1, //
+                3, 1, 2, 6, 4, 5, //
+                6, 4, 5, 3, 1, 2, //
+            ][..]
+        );
+        assert_eq!(
+            board.to_string(),
+            "\
++------+------+
+|1 2 3 |4 5 6 |
+|4 5 6 |1 2 3 |
++------+------+
+|2 3 1 |5 6 4 |
+|5 6 4 |2 3 1 |
++------+------+
+|3 1 2 |6 4 5 |
+|6 4 5 |3 1 2 |
++------+------+
+"
+        );
+    }
+
+    #[test]
+    fn test_rectangular_shape() {
+        assert_eq!(
+            Board::rectangular(SIX.to_vec(), 3, 3),
+            Err(SudokuError::InvalidShape {
+                cells: 36,
+                box_w: 3,
+                box_h: 3
+            })
+        );
+        assert!(Board::rectangular(vec![], 0, 2).is_err());
+
+        let mut grid = SIX.to_vec();
+        grid[1] = 7;
+        assert_eq!(
+            Board::rectangular(grid, 3, 2),
+            Err(SudokuError::InvalidDigit { cell: 1, digit: 7 })
+        );
+
+        // The same engine solves the classic board
+        let mut classic = Board::rectangular(vec![0; 81], 3, 3).unwrap();
+        assert!(classic.solve());
+        assert!(classic.cells().iter().all(|&d| (1..=9).contains(&d)));
+    }
+
+    // Generated for this test, 17 clues are not enough for a unique
+    // solution without the windows
+    const HYPER: &str =
+        ".....7..8.....94.....2...3.7........9........6...5.........29..8.2....5..9.6.....";
+
+    fn grid(line: &str) -> Vec<u8> {
+        line.chars()
+            .map(|c| c.to_digit(10).unwrap_or(0) as u8)
+            .collect()
+    }
+
+    #[test]
+    fn test_hyper() {
+        let mut board = Sudoku::hyper(grid(HYPER)).unwrap();
+        assert!(board.is_unique());
+        assert!(board.solve());
+        assert_eq!(
+            board.cells(),
+            &grid(
+                "254367198376189425189245736735426819921873564648951372463512987812794653597638241"
+            )[..]
+        );
+
+        let classic = Board::rectangular(grid(HYPER), 3, 3).unwrap();
+        assert!(!classic.is_unique());
+        assert!(!Sudoku::try_from(HYPER).unwrap().is_unique());
+
+        assert!(Sudoku::hyper(vec![0; 80]).is_err());
+    }
+
+    #[test]
+    fn test_inequalities() {
+        let mut grid = vec![0; 16];
+        grid[5] = 4;
+        grid[10] = 4;
+        let board = Board::rectangular(grid, 2, 2).unwrap();
+        assert!(!board.is_unique());
+
+        let mut board = board
+            .with_inequalities(vec![(0, 1), (1, 2), (6, 7), (9, 13)])
+            .unwrap();
+        assert!(board.is_unique());
+        assert!(board.solve());
+        assert_eq!(
+            board.cells(),
+            &[
+                1, 2, 3, 4, //
+                3, 4, 1, 2, //
+                2, 1, 4, 3, //
+                4, 3, 2, 1, //
+            ][..]
+        );
+
+        // 4 can't be smaller than anything
+        let mut grid = vec![0; 16];
+        grid[0] = 4;
+        let mut board = Board::rectangular(grid, 2, 2)
+            .unwrap()
+            .with_inequalities(vec![(0, 1)])
+            .unwrap();
+        assert!(!board.solve());
+
+        assert_eq!(
+            board.with_inequalities(vec![(3, 16)]).unwrap_err(),
+            SudokuError::InvalidCell { cell: 16 }
+        );
+    }
+
+    #[test]
+    fn test_parity() {
+        let mut grid = vec![0; 16];
+        grid[0] = 1;
+        grid[1] = 2;
+        grid[8] = 2;
+        grid[9] = 1;
+        let board = Board::rectangular(grid, 2, 2).unwrap();
+        assert!(!board.is_unique());
+
+        let mut board = board
+            .with_parity_constraint(5, Parity::Even)
+            .and_then(|board| board.with_parity_constraint(10, Parity::Even))
+            .and_then(|board| board.with_parity_constraint(15, Parity::Odd))
+            .unwrap();
+        assert!(board.is_unique());
+        assert!(board.solve());
+        assert_eq!(
+            board.cells(),
+            &[
+                1, 2, 3, 4, //
+                3, 4, 1, 2, //
+                2, 1, 4, 3, //
+                4, 3, 2, 1, //
+            ][..]
+        );
+
+        // A clue of the wrong parity
+        let mut grid = vec![0; 16];
+        grid[0] = 1;
+        let mut board = Board::rectangular(grid, 2, 2)
+            .unwrap()
+            .with_parity_constraint(0, Parity::Even)
+            .unwrap();
+        assert!(!board.solve());
+
+        assert_eq!(
+            board.with_parity_constraint(16, Parity::Odd).unwrap_err(),
+            SudokuError::InvalidCell { cell: 16 }
+        );
+    }
+
+    #[test]
+    fn test_anti_knight() {
+        // Generated for this test, 13 clues are far from enough for a
+        // classic sudoku
+        let puzzle = grid(
+            ".3...6..............6........5.....14..98....................2.......65..9.....3.",
+        );
+        assert!(!Board::rectangular(puzzle.clone(), 3, 3)
+            .unwrap()
+            .is_unique());
+
+        let mut board = Board::rectangular(puzzle, 3, 3)
+            .unwrap()
+            .with_variant(SudokuVariant::AntiKnight);
+        assert!(board.is_unique());
+        assert!(board.solve());
+        assert_eq!(
+            board.cells(),
+            &grid(
+                "734516892519824376826379415265437981471982563983165247658743129347291658192658734"
+            )[..]
+        );
+
+        // The same digit a knight's move apart
+        let mut cells = vec![0; 81];
+        cells[2] = 5;
+        cells[13] = 5;
+        assert!(Board::rectangular(cells.clone(), 3, 3).unwrap().solve());
+        let mut board = Board::rectangular(cells, 3, 3)
+            .unwrap()
+            .with_variant(SudokuVariant::AntiKnight);
+        assert!(!board.solve());
+    }
+}
@@ -0,0 +1,146 @@
+//! C API, enabled with the `capi` feature.
+//!
+//! A C library can be built with
+//!
+//! ```sh
+//! cargo rustc --release --lib --features capi --crate-type cdylib
+//! ```
+//!
+//! (or `staticlib`) and used through `include/sudoku_solver.h`.
+//!
+//! Puzzles are passed as NUL terminated strings read the same way as
+//! `Sudoku::try_from` reads them: 81 digits or dots, where a `0` or a
+//! `.` are an empty cell, and anything else is ignored.  Solutions are
+//! written as a NUL terminated line of 81 digits, so the output buffer
+//! must be at least `SUDOKU_LINE_LEN` (82) bytes long.
+//!
+//! None of the functions keep the pointers they get after returning,
+//! and all of them are thread safe.
+
+use std::convert::TryFrom;
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+
+use crate::Sudoku;
+
+/// Bytes needed to hold a solution: 81 digits and a NUL
+pub const SUDOKU_LINE_LEN: usize = 82;
+
+/// The puzzle was solved
+pub const SUDOKU_SOLVED: c_int = 0;
+/// The puzzle has no solution
+pub const SUDOKU_UNSOLVABLE: c_int = 1;
+/// The input could not be read as a puzzle, or it is a null pointer
+pub const SUDOKU_PARSE_ERROR: c_int = 2;
+/// The output buffer is shorter than `SUDOKU_LINE_LEN`, or it is a
+/// null pointer
+pub const SUDOKU_BUFFER_TOO_SMALL: c_int = 3;
+
+/// Reads the puzzle behind `input`, if any
+unsafe fn parse(input: *const c_char) -> Option<Sudoku> {
+    if input.is_null() {
+        return None;
+    }
+    let input = CStr::from_ptr(input).to_str().ok()?;
+    Sudoku::try_from(input).ok()
+}
+
+/// Solves the puzzle in `input` and writes its solution to `out`.
+///
+/// Returns `SUDOKU_SOLVED`, `SUDOKU_UNSOLVABLE`, `SUDOKU_PARSE_ERROR`
+/// or `SUDOKU_BUFFER_TOO_SMALL`.  `out` is only written to when the
+/// puzzle is solved.
+///
+/// # Safety
+///
+/// `input` must be null or point to a NUL terminated string, and `out`
+/// must be null or point to at least `out_len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn sudoku_solve(
+    input: *const c_char,
+    out: *mut c_char,
+    out_len: usize,
+) -> c_int {
+    let mut sudoku = match parse(input) {
+        Some(sudoku) => sudoku,
+        None => return SUDOKU_PARSE_ERROR,
+    };
+
+    if out.is_null() || out_len < SUDOKU_LINE_LEN {
+        return SUDOKU_BUFFER_TOO_SMALL;
+    }
+
+    if !sudoku.solve() {
+        return SUDOKU_UNSOLVABLE;
+    }
+
+    let line = sudoku.to_line();
+    ptr::copy_nonoverlapping(line.as_ptr() as *const c_char, out, line.len());
+    *out.add(line.len()) = 0;
+    SUDOKU_SOLVED
+}
+
+/// Returns 1 if the puzzle in `input` has exactly one solution, 0 if
+/// it has none or more than one and `SUDOKU_PARSE_ERROR` if it can't
+/// be read.
+///
+/// # Safety
+///
+/// `input` must be null or point to a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn sudoku_is_unique(input: *const c_char) -> c_int {
+    match parse(input) {
+        Some(sudoku) => sudoku.is_unique() as c_int,
+        None => SUDOKU_PARSE_ERROR,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::ffi::CString;
+
+    const HARD: &str =
+        "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......";
+    const SOLUTION: &str =
+        "417369825632158947958724316825437169791586432346912758289643571573291684164875293";
+
+    #[test]
+    fn test_sudoku_solve() {
+        let input = CString::new(HARD).unwrap();
+        let mut out = [1 as c_char; SUDOKU_LINE_LEN];
+        let status = unsafe { sudoku_solve(input.as_ptr(), out.as_mut_ptr(), out.len()) };
+        assert_eq!(status, SUDOKU_SOLVED);
+        let solution = unsafe { CStr::from_ptr(out.as_ptr()) };
+        assert_eq!(solution.to_str(), Ok(SOLUTION));
+
+        let status = unsafe { sudoku_solve(input.as_ptr(), out.as_mut_ptr(), 81) };
+        assert_eq!(status, SUDOKU_BUFFER_TOO_SMALL);
+        let status = unsafe { sudoku_solve(input.as_ptr(), ptr::null_mut(), 100) };
+        assert_eq!(status, SUDOKU_BUFFER_TOO_SMALL);
+
+        let unsolvable = CString::new(HARD.replacen('.', "4", 1)).unwrap();
+        let status = unsafe { sudoku_solve(unsolvable.as_ptr(), out.as_mut_ptr(), out.len()) };
+        assert_eq!(status, SUDOKU_UNSOLVABLE);
+
+        let short = CString::new(&HARD[1..]).unwrap();
+        let status = unsafe { sudoku_solve(short.as_ptr(), out.as_mut_ptr(), out.len()) };
+        assert_eq!(status, SUDOKU_PARSE_ERROR);
+        let status = unsafe { sudoku_solve(ptr::null(), out.as_mut_ptr(), out.len()) };
+        assert_eq!(status, SUDOKU_PARSE_ERROR);
+    }
+
+    #[test]
+    fn test_sudoku_is_unique() {
+        let unique = CString::new(HARD).unwrap();
+        let empty = CString::new(".".repeat(81)).unwrap();
+        let short = CString::new("123").unwrap();
+        unsafe {
+            assert_eq!(sudoku_is_unique(unique.as_ptr()), 1);
+            assert_eq!(sudoku_is_unique(empty.as_ptr()), 0);
+            assert_eq!(sudoku_is_unique(short.as_ptr()), SUDOKU_PARSE_ERROR);
+            assert_eq!(sudoku_is_unique(ptr::null()), SUDOKU_PARSE_ERROR);
+        }
+    }
+}
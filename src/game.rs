@@ -0,0 +1,297 @@
+//! Playing a puzzle by hand: the clues stay fixed and every move is
+//! checked against the digits already in place.
+//!
+//! A `Sudoku` keeps the moves played on it, so the digits placed by
+//! the player are told apart from the clues, can be changed again and
+//! the moves undone.  A `Game` adds the player's pencil marks.
+
+use std::fmt;
+
+use crate::{Possible, Sudoku};
+
+/// The moves played on a `Sudoku`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct History {
+    /// A cell and the digit it had before every move, the last one at
+    /// the end
+    undo: Vec<(usize, u8)>,
+    /// Same for the moves undone, cleared by any new move
+    redo: Vec<(usize, u8)>,
+}
+
+impl History {
+    /// Drops the moves on `cell`, to make its digit a clue
+    pub(crate) fn forget(&mut self, cell: usize) {
+        self.undo.retain(|&(played, _)| played != cell);
+        self.redo.retain(|&(played, _)| played != cell);
+    }
+}
+
+impl Sudoku {
+    /// Whether `cell` is one of the clues: filled in, but not by
+    /// `play`.
+    pub fn is_given(&self, cell: usize) -> bool {
+        self.0[cell] != 0 && self.1.undo.iter().all(|&(played, _)| played != cell)
+    }
+
+    /// Places `digit` in `cell`, or clears it with a 0.  The digit
+    /// can't be anywhere else in the row, column or square of the
+    /// cell, and clues can't be changed.  Whether the digit is the one
+    /// in the solution is not checked.
+    pub fn play(&mut self, cell: usize, digit: u8) -> Result<(), MoveError> {
+        self.check_move(cell, digit)?;
+        self.1.undo.push((cell, self.0[cell]));
+        self.1.redo.clear();
+        self.0[cell] = digit;
+        Ok(())
+    }
+
+    /// Why `play` would refuse the move, if it would
+    pub(crate) fn check_move(&self, cell: usize, digit: u8) -> Result<(), MoveError> {
+        if cell >= 81 {
+            return Err(MoveError::InvalidCell(cell));
+        }
+        if digit > 9 {
+            return Err(MoveError::InvalidDigit(digit));
+        }
+        if self.is_given(cell) {
+            return Err(MoveError::CellIsGiven);
+        }
+        if digit != 0 {
+            let peers = Sudoku::peers(cell as u8).map(usize::from);
+            if let Some(other) = peers.filter(|&p| self.0[p] == digit).min() {
+                return Err(MoveError::Conflict(other));
+            }
+        }
+        Ok(())
+    }
+
+    /// Takes back the last move that was not undone yet, the cell gets
+    /// the digit it had before, 0 if it was empty.  Returns false if
+    /// there is none.
+    pub fn undo(&mut self) -> bool {
+        let History { undo, redo } = &mut self.1;
+        Sudoku::replay(&mut self.0, undo, redo)
+    }
+
+    /// Plays again the last move undone, unless there were other moves
+    /// since.  Returns false if there is none.
+    pub fn redo(&mut self) -> bool {
+        let History { undo, redo } = &mut self.1;
+        Sudoku::replay(&mut self.0, redo, undo)
+    }
+
+    /// Sets the last cell in `from` back to its digit there, and keeps
+    /// the digit it had in `to`
+    fn replay(grid: &mut [u8; 81], from: &mut Vec<(usize, u8)>, to: &mut Vec<(usize, u8)>) -> bool {
+        match from.pop() {
+            Some((cell, digit)) => {
+                to.push((cell, grid[cell]));
+                grid[cell] = digit;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// A puzzle being played, with the player's pencil marks.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Game {
+    /// The clues, the digits placed so far and the moves
+    sudoku: Sudoku,
+    /// The digits the player noted as possible in every cell
+    user_marks: [Possible; 81],
+}
+
+impl Game {
+    /// Starts playing `puzzle`, with no pencil marks.
+    pub fn new(puzzle: Sudoku) -> Self {
+        Game {
+            sudoku: puzzle,
+            user_marks: [Possible(0); 81],
+        }
+    }
+
+    /// The puzzle with the digits placed so far
+    pub fn sudoku(&self) -> &Sudoku {
+        &self.sudoku
+    }
+
+    /// The puzzle, to play moves on it and undo them, see
+    /// `Sudoku::play`.  The pencil marks stay as they are.
+    pub fn sudoku_mut(&mut self) -> &mut Sudoku {
+        &mut self.sudoku
+    }
+
+    /// The pencil marks of `cell`, none when the game starts
+    pub fn marks(&self, cell: usize) -> Possible {
+        self.user_marks[cell]
+    }
+
+    /// Adds `digit` to the pencil marks of `cell`, or takes it away if
+    /// it was there.  Panics unless `digit` is between 1 and 9.
+    pub fn toggle_mark(&mut self, cell: usize, digit: u8) {
+        assert!((1..=9).contains(&digit), "{} is not a digit", digit);
+        self.user_marks[cell] = self.user_marks[cell].toggle(digit);
+    }
+
+    /// Replaces the pencil marks of every cell with its possible values
+    /// after constraint propagation from the current grid, a single one
+    /// in the filled cells.  If the grid is contradictory no cell is
+    /// left with marks.
+    pub fn auto_fill_marks(&mut self) {
+        let values = self.sudoku.candidates();
+        for (cell, marks) in self.user_marks.iter_mut().enumerate() {
+            *marks = values.candidates(cell);
+        }
+    }
+}
+
+/// Why `Sudoku::play` refused a move
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MoveError {
+    /// The digit is already in this other cell, the lowest one if
+    /// there are several
+    Conflict(usize),
+    /// The cell is one of the clues
+    CellIsGiven,
+    /// The cell is not below 81
+    InvalidCell(usize),
+    /// The digit is over 9
+    InvalidDigit(u8),
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveError::Conflict(other) => write!(f, "the digit is already in cell {}", other),
+            MoveError::CellIsGiven => write!(f, "clues can't be changed"),
+            MoveError::InvalidCell(cell) => write!(f, "{} is not a cell", cell),
+            MoveError::InvalidDigit(digit) => write!(f, "{} is not a digit", digit),
+        }
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::convert::TryFrom;
+
+    const HARD: &str =
+        "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......";
+
+    #[test]
+    fn test_play() {
+        let puzzle = Sudoku::try_from(HARD).unwrap();
+        let mut sudoku = puzzle.clone();
+        assert!(sudoku.is_given(0));
+        assert!(!sudoku.is_given(1));
+
+        assert_eq!(sudoku.play(1, 1), Ok(()));
+        assert_eq!(sudoku.0[1], 1);
+        assert!(!sudoku.is_given(1));
+        // Changing our mind is fine
+        assert_eq!(sudoku.play(1, 7), Ok(()));
+        assert_eq!(sudoku.play(1, 0), Ok(()));
+        assert_eq!(sudoku.to_line(), puzzle.to_line());
+
+        // There is a 4 in the row, and a 3 in the square
+        assert_eq!(sudoku.play(1, 4), Err(MoveError::Conflict(0)));
+        assert_eq!(sudoku.play(1, 3), Err(MoveError::Conflict(10)));
+        assert_eq!(sudoku.play(0, 1), Err(MoveError::CellIsGiven));
+        assert_eq!(sudoku.play(0, 0), Err(MoveError::CellIsGiven));
+        assert_eq!(sudoku.play(81, 1), Err(MoveError::InvalidCell(81)));
+        assert_eq!(sudoku.play(1, 10), Err(MoveError::InvalidDigit(10)));
+        assert_eq!(sudoku.to_line(), puzzle.to_line());
+
+        let mut solution = puzzle.clone();
+        solution.solve();
+        for cell in 0..81 {
+            if !puzzle.is_given(cell) {
+                assert!(!sudoku.is_solved());
+                assert_eq!(sudoku.play(cell, solution.0[cell]), Ok(()));
+            }
+        }
+        assert!(sudoku.is_solved());
+        // The digits played are still not clues
+        assert_eq!(sudoku.givens(), puzzle.givens());
+        assert_eq!(sudoku.play(1, 0), Ok(()));
+    }
+
+    #[test]
+    fn test_undo() {
+        let puzzle = Sudoku::try_from(HARD).unwrap();
+        let mut sudoku = puzzle.clone();
+        assert!(!sudoku.undo());
+        assert!(!sudoku.redo());
+
+        sudoku.play(1, 1).unwrap();
+        sudoku.play(2, 7).unwrap();
+        sudoku.play(1, 9).unwrap();
+        // Refused moves are not in the history
+        assert!(sudoku.play(0, 1).is_err());
+
+        assert!(sudoku.undo());
+        assert_eq!(sudoku.0[1], 1);
+        assert!(sudoku.undo());
+        assert_eq!(sudoku.0[2], 0);
+        assert!(sudoku.undo());
+        assert_eq!(sudoku.to_line(), puzzle.to_line());
+        assert_eq!(sudoku.givens(), puzzle.givens());
+        assert!(!sudoku.undo());
+    }
+
+    #[test]
+    fn test_redo() {
+        let puzzle = Sudoku::try_from(HARD).unwrap();
+        let mut sudoku = puzzle.clone();
+        sudoku.play(1, 1).unwrap();
+        sudoku.play(2, 7).unwrap();
+        sudoku.play(1, 9).unwrap();
+        let played = sudoku.clone();
+        while sudoku.undo() {}
+
+        assert!(sudoku.redo());
+        assert!(sudoku.redo());
+        assert!(sudoku.redo());
+        assert_eq!(sudoku, played);
+        assert!(!sudoku.redo());
+
+        // A new move forgets what was undone
+        assert!(sudoku.undo());
+        sudoku.play(1, 0).unwrap();
+        assert!(!sudoku.redo());
+        assert!(sudoku.undo());
+        assert_eq!((sudoku.0[1], sudoku.0[2]), (1, 7));
+        assert!(!sudoku.is_given(1));
+    }
+
+    #[test]
+    fn test_marks() {
+        let mut game = Game::new(Sudoku::try_from(HARD).unwrap());
+        assert!(game.marks(1).is_empty());
+        game.toggle_mark(1, 2);
+        game.toggle_mark(1, 6);
+        game.toggle_mark(1, 2);
+        assert_eq!(game.marks(1).values().collect::<Vec<_>>(), vec![6]);
+
+        game.sudoku_mut().play(1, 1).unwrap();
+        assert_eq!(game.marks(1).values().collect::<Vec<_>>(), vec![6]);
+        game.auto_fill_marks();
+        // The clues and the digits played only have themselves
+        assert_eq!(game.marks(0).values().collect::<Vec<_>>(), vec![4]);
+        assert_eq!(game.marks(1).values().collect::<Vec<_>>(), vec![1]);
+        // No other cell of the first row can have a 1 now
+        assert!((2..9).all(|c| !game.marks(c).contains(1)));
+        assert!((0..81).any(|c| game.marks(c).len() > 1));
+
+        // A 4 next to the one in the corner leaves nothing possible
+        let mut wrong = Game::new(Sudoku::try_from(HARD).unwrap());
+        wrong.sudoku.0[1] = 4;
+        wrong.auto_fill_marks();
+        assert!((0..81).all(|c| wrong.marks(c).is_empty()));
+    }
+}
@@ -0,0 +1,353 @@
+//! Random puzzle generation
+
+use std::fmt;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::{Difficulty, History, Sudoku, Values};
+
+/// What `generate` found out about the puzzle it made
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GenerationReport {
+    /// Number of non-empty cells
+    pub clues: usize,
+    /// The puzzle has a single solution.  Always `true`: clues are only
+    /// removed while that holds, so it needs no checking again.
+    pub unique: bool,
+    pub difficulty: Difficulty,
+}
+
+/// How many puzzles `generate` makes before giving up on finding one
+/// that meets its options
+pub(crate) const MAX_ATTEMPTS: usize = 100;
+
+/// Fewer clues never have a single solution
+pub(crate) const MIN_CLUES: usize = 17;
+
+/// What `generate` must make
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GenerateOptions {
+    /// The pattern of the clues
+    pub symmetry: Symmetry,
+    /// Keep removing clues until none can go without making the puzzle
+    /// ambiguous, or breaking its symmetry.  Otherwise stop as soon as
+    /// there are no more than `max_clues`.  Without `max_clues` the
+    /// puzzle is minimal either way.
+    pub minimal: bool,
+    /// Only puzzles rated this, any if `None`
+    pub difficulty: Option<Difficulty>,
+    /// Only puzzles with this many clues or less, any if `None`
+    pub max_clues: Option<usize>,
+}
+
+impl Default for GenerateOptions {
+    /// Minimal puzzles without any other requirement
+    fn default() -> Self {
+        GenerateOptions {
+            symmetry: Symmetry::None,
+            minimal: true,
+            difficulty: None,
+            max_clues: None,
+        }
+    }
+}
+
+/// A pattern the clues of a puzzle follow: every clue has another
+/// one in its mirror cell
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Symmetry {
+    None,
+    /// Turning the grid half a circle around its center
+    Rotational,
+    /// Mirrored across the middle row: top and bottom swap
+    Horizontal,
+    /// Mirrored across the middle column: left and right swap
+    Vertical,
+    /// Mirrored across the diagonal from the top left corner
+    Diagonal,
+}
+
+impl Symmetry {
+    /// Every symmetry but `None`, from the most usual in published
+    /// puzzles
+    pub(crate) const ALL: [Symmetry; 4] = [
+        Symmetry::Rotational,
+        Symmetry::Horizontal,
+        Symmetry::Vertical,
+        Symmetry::Diagonal,
+    ];
+
+    /// The cell `cell` is mirrored into, maybe itself
+    pub(crate) fn mirror(self, cell: usize) -> usize {
+        let (row, column) = (cell / 9, cell % 9);
+        match self {
+            Symmetry::None => cell,
+            Symmetry::Rotational => 80 - cell,
+            Symmetry::Horizontal => (8 - row) * 9 + column,
+            Symmetry::Vertical => row * 9 + 8 - column,
+            Symmetry::Diagonal => column * 9 + row,
+        }
+    }
+}
+
+/// Why `generate` made no puzzle
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GenerateError {
+    /// No puzzle with so few clues has a single solution
+    TooFewClues(usize),
+    /// None of the puzzles made met the options, they may be too hard
+    /// to meet together
+    GaveUp { attempts: usize },
+}
+
+impl fmt::Display for GenerateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GenerateError::TooFewClues(clues) => write!(
+                f,
+                "puzzles with {} clues don't have a single solution, at least {} are needed",
+                clues, MIN_CLUES
+            ),
+            GenerateError::GaveUp { attempts } => {
+                write!(f, "no puzzle met the options after {} attempts", attempts)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GenerateError {}
+
+/// Generates a random puzzle with a unique solution, following
+/// `options`.
+///
+/// Starts from a random solved grid and empties its cells in random
+/// order, together with their mirror cell, putting the digits back
+/// whenever the puzzle stops having a single solution.  Puzzles that
+/// don't meet the clue count or difficulty asked for are thrown away
+/// and another one is made, up to a hundred times.
+pub fn generate<R: Rng + ?Sized>(
+    rng: &mut R,
+    options: GenerateOptions,
+) -> Result<(Sudoku, GenerationReport), GenerateError> {
+    if let Some(clues) = options.max_clues.filter(|&c| c < MIN_CLUES) {
+        return Err(GenerateError::TooFewClues(clues));
+    }
+
+    for _ in 0..MAX_ATTEMPTS {
+        let puzzle = remove_clues(random_solution(rng), rng, &options);
+        let clues = puzzle.clue_count();
+        if options.max_clues.is_some_and(|max| clues > max) {
+            continue;
+        }
+        // We can unwrap safely because the puzzle comes from a
+        // solution
+        let difficulty = puzzle.difficulty().unwrap();
+        if options.difficulty.is_some_and(|d| d != difficulty) {
+            continue;
+        }
+        let report = GenerationReport {
+            clues,
+            unique: true,
+            difficulty,
+        };
+        return Ok((puzzle, report));
+    }
+    Err(GenerateError::GaveUp {
+        attempts: MAX_ATTEMPTS,
+    })
+}
+
+/// An endless supply of puzzles of `difficulty`, each with a unique
+/// solution, made by `generate` one at a time as they are asked for.
+/// The same `seed` always gives the same puzzles.
+pub fn puzzle_stream(seed: u64, difficulty: Difficulty) -> impl Iterator<Item = Sudoku> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let options = GenerateOptions {
+        difficulty: Some(difficulty),
+        ..GenerateOptions::default()
+    };
+    // `generate` only gives up after many attempts, so keep trying
+    std::iter::from_fn(move || loop {
+        if let Ok((puzzle, _)) = generate(&mut rng, options) {
+            return Some(puzzle);
+        }
+    })
+}
+
+/// Empties the cells of `puzzle` in random order, each one with its
+/// mirror cell, as long as it keeps a single solution
+pub(crate) fn remove_clues<R: Rng + ?Sized>(
+    mut puzzle: Sudoku,
+    rng: &mut R,
+    options: &GenerateOptions,
+) -> Sudoku {
+    let enough = options.max_clues.filter(|_| !options.minimal);
+    let mut cells: Vec<usize> = (0..81).collect();
+    cells.shuffle(rng);
+    for cell in cells {
+        if enough.is_some_and(|max| puzzle.clue_count() <= max) {
+            break;
+        }
+        let mirror = options.symmetry.mirror(cell);
+        let digits = (puzzle.0[cell], puzzle.0[mirror]);
+        if digits.0 == 0 {
+            continue;
+        }
+        puzzle.0[cell] = 0;
+        puzzle.0[mirror] = 0;
+        if !puzzle.is_unique() {
+            puzzle.0[cell] = digits.0;
+            puzzle.0[mirror] = digits.1;
+        }
+    }
+    puzzle
+}
+
+/// A random solved grid
+fn random_solution<R: Rng + ?Sized>(rng: &mut R) -> Sudoku {
+    loop {
+        if let Some(grid) = random_values(rng).and_then(|values| values.solved_grid()) {
+            return Sudoku(grid, History::default());
+        }
+    }
+}
+
+/// Visits the cells in random order assigning them one of their
+/// possible values at random.  Propagation makes sure the result
+/// is a valid grid, unless a contradiction is found on the way.
+fn random_values<R: Rng + ?Sized>(rng: &mut R) -> Option<Values> {
+    let mut values = Values::new();
+
+    let mut cells: Vec<usize> = (0..81).collect();
+    cells.shuffle(rng);
+    for cell in cells {
+        if values.0[cell].len() > 1 {
+            let digits: Vec<u8> = values.0[cell].values().collect();
+            let &digit = digits.choose(rng)?;
+            values = values.assign(digit, cell).ok()?;
+        }
+    }
+    Some(values)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Every row, column and square has all the digits
+    fn is_valid_solution(grid: &[u8; 81]) -> bool {
+        (0..81u8).all(|cell| {
+            Sudoku::units(cell).iter().all(|unit| {
+                let mut digits: Vec<u8> = unit.iter().map(|&c| grid[c as usize]).collect();
+                digits.push(grid[cell as usize]);
+                digits.sort_unstable();
+                digits == (1..=9).collect::<Vec<_>>()
+            })
+        })
+    }
+
+    #[test]
+    fn test_random_solution() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let a = random_solution(&mut rng);
+        let b = random_solution(&mut rng);
+        assert!(is_valid_solution(&a.0));
+        assert!(is_valid_solution(&b.0));
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_generate() {
+        let mut rng = StdRng::seed_from_u64(42);
+        for _ in 0..3 {
+            let (puzzle, report) = generate(&mut rng, GenerateOptions::default()).unwrap();
+            assert!(report.unique);
+            assert!(puzzle.is_unique());
+            assert_eq!(report.clues, puzzle.0.iter().filter(|&&d| d != 0).count());
+            assert!(report.clues >= 17);
+            assert_eq!(Some(report.difficulty), puzzle.difficulty());
+
+            let mut solution = puzzle.clone();
+            assert!(solution.solve());
+            assert!(is_valid_solution(&solution.0));
+
+            // Minimal, removing any clue makes it ambiguous
+            for cell in (0..81).filter(|&c| puzzle.0[c] != 0) {
+                let mut fewer = puzzle.clone();
+                fewer.0[cell] = 0;
+                assert!(!fewer.is_unique());
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate_options() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let symmetries = [
+            Symmetry::Rotational,
+            Symmetry::Horizontal,
+            Symmetry::Vertical,
+            Symmetry::Diagonal,
+        ];
+        for &symmetry in symmetries.iter() {
+            let options = GenerateOptions {
+                symmetry,
+                difficulty: Some(Difficulty::Easy),
+                ..GenerateOptions::default()
+            };
+            let (puzzle, report) = generate(&mut rng, options).unwrap();
+            assert!(puzzle.is_unique());
+            assert_eq!(puzzle.difficulty(), Some(Difficulty::Easy));
+            assert_eq!(report.difficulty, Difficulty::Easy);
+            for cell in 0..81 {
+                let mirror = symmetry.mirror(cell);
+                assert_eq!(puzzle.0[cell] == 0, puzzle.0[mirror] == 0);
+            }
+
+            // Minimal: no clue can go together with its mirror
+            for cell in (0..81).filter(|&c| puzzle.0[c] != 0) {
+                let mut fewer = puzzle.clone();
+                fewer.0[cell] = 0;
+                fewer.0[symmetry.mirror(cell)] = 0;
+                assert!(!fewer.is_unique());
+            }
+        }
+
+        let options = GenerateOptions {
+            minimal: false,
+            max_clues: Some(40),
+            ..GenerateOptions::default()
+        };
+        let (puzzle, report) = generate(&mut rng, options).unwrap();
+        assert!(puzzle.is_unique());
+        assert_eq!(report.clues, 40);
+    }
+
+    #[test]
+    fn test_puzzle_stream() {
+        let puzzles: Vec<Sudoku> = puzzle_stream(5, Difficulty::Medium).take(3).collect();
+        for (i, puzzle) in puzzles.iter().enumerate() {
+            assert!(puzzle.is_unique());
+            assert_eq!(puzzle.difficulty(), Some(Difficulty::Medium));
+            assert!(puzzles[..i].iter().all(|other| other != puzzle));
+        }
+        let again: Vec<Sudoku> = puzzle_stream(5, Difficulty::Medium).take(3).collect();
+        assert_eq!(again, puzzles);
+    }
+
+    #[test]
+    fn test_generate_impossible() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let too_few = GenerateOptions {
+            symmetry: Symmetry::Rotational,
+            max_clues: Some(16),
+            ..GenerateOptions::default()
+        };
+        assert_eq!(
+            generate(&mut rng, too_few),
+            Err(GenerateError::TooFewClues(16))
+        );
+    }
+}
@@ -0,0 +1,2971 @@
+//! Sudoku solver
+//! Based on this excelent writeup <http://norvig.com/sudoku.html>
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+use rand::rngs::StdRng;
+use rand::Rng;
+
+/// `log::trace!` to the `sudoku::search` target with the `log` feature,
+/// nothing at all without it
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "log")]
+        log::trace!(target: "sudoku::search", $($arg)*);
+    };
+}
+
+/// Same as `trace!`, with `log::debug!`
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "log")]
+        log::debug!(target: "sudoku::search", $($arg)*);
+    };
+}
+
+mod board;
+#[cfg(feature = "capi")]
+pub mod capi;
+mod game;
+mod generator;
+mod possible;
+#[cfg(feature = "python")]
+mod python;
+mod samurai;
+mod solver;
+#[cfg(feature = "svg")]
+mod svg;
+mod techniques;
+mod transform;
+
+pub use board::{Board, Parity, SudokuVariant};
+pub use game::{Game, MoveError};
+pub use generator::{
+    generate, puzzle_stream, GenerateError, GenerateOptions, GenerationReport, Symmetry,
+};
+pub use possible::{BitSet, Possible};
+pub use samurai::Samurai;
+pub use solver::{SolveResult, SolveStatus, Solver};
+pub use techniques::{Strategy, Technique};
+
+use game::History;
+
+/// `Search` holds the limits and the bookkeeping of a single run of
+/// `Values::search`.
+struct Search<'a> {
+    stats: SolveStats,
+    /// How many nested guesses are allowed before giving up
+    max_depth: u32,
+    /// Set when a limit was hit, as opposed to having exhausted all
+    /// the possibilities
+    aborted: bool,
+    /// Called every `PROGRESS_EVERY` guesses
+    progress: Option<&'a mut dyn FnMut(SolveProgress)>,
+    /// Checked before every guess, the search gives up once it is set
+    cancel: Option<&'a AtomicBool>,
+    /// Same, the search gives up once it is past
+    deadline: Option<Instant>,
+    /// How to pick the cell to guess on
+    heuristic: Heuristic,
+    /// For `Heuristic::Random`, only a `Solver` has one
+    rng: Option<StdRng>,
+}
+
+/// How many guesses go between two calls to the progress callback
+const PROGRESS_EVERY: usize = 100;
+
+impl<'a> Search<'a> {
+    fn new() -> Self {
+        Search {
+            stats: SolveStats::default(),
+            max_depth: u32::MAX,
+            aborted: false,
+            progress: None,
+            cancel: None,
+            deadline: None,
+            heuristic: Heuristic::MostConstrained,
+            rng: None,
+        }
+    }
+
+    /// Whether to give up instead of making another guess, because of
+    /// a limit or a cancellation.  Once it says so it keeps saying so.
+    fn should_stop(&mut self) -> bool {
+        if self.cancel.is_some_and(|c| c.load(Ordering::Relaxed))
+            || self.deadline.is_some_and(|d| Instant::now() >= d)
+        {
+            self.aborted = true;
+        }
+        self.aborted
+    }
+
+    /// The cell of `values` to guess on, which must have one with more
+    /// than one possible value
+    fn pick(&mut self, values: &Values) -> usize {
+        match (self.heuristic, &mut self.rng) {
+            (Heuristic::FirstEmpty, _) => values.0.iter().position(|p| p.len() > 1).unwrap(),
+            (Heuristic::Random, Some(rng)) => {
+                let fewest = values.0[values.most_constrained()].len();
+                // Every cell with the fewest values gets the same
+                // chance, without collecting them
+                let mut picked = 0;
+                let tied = values
+                    .0
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, p)| p.len() == fewest);
+                for (seen, (cell, _)) in tied.enumerate() {
+                    if rng.gen_range(0..=seen) == 0 {
+                        picked = cell;
+                    }
+                }
+                picked
+            }
+            _ => values.most_constrained(),
+        }
+    }
+
+    fn report(&mut self, values: &Values) {
+        if let Some(progress) = &mut self.progress {
+            progress(SolveProgress {
+                cells_solved: values.0.iter().filter(|p| p.len() == 1).count(),
+                guesses: self.stats.guesses,
+            });
+        }
+    }
+}
+
+/// A frame of `Values::search_iterative`: the state we are guessing
+/// on, the cell chosen and the values not tried yet for that cell
+type Guess = (Values, usize, Possible);
+
+/// `Values` stores all the possible values for every cell in the
+/// sudoku.  Its core is the search function, that uses constraint
+/// propagation and backtracking to find a possible solution to the
+/// sudoku.
+///
+/// It is a plain array, so every guess copies it without allocating.
+///
+/// A `Strategy` gets to see and remove the possible values.
+#[derive(Copy, Clone, Debug)]
+pub struct Values([Possible; 81]);
+
+impl Values {
+    /// The possible values of `cell`.  Panics if `cell` is not below
+    /// 81.
+    pub fn candidates(&self, cell: usize) -> Possible {
+        assert!(cell < 81, "{} is not a cell", cell);
+        self.0[cell]
+    }
+
+    /// The digits of every cell, row by row, if every one of them has
+    /// a single possible value.  They follow the rules as long as the
+    /// values come from the propagation.
+    pub fn solved_grid(&self) -> Option<[u8; 81]> {
+        let mut grid = [0u8; 81];
+        for (d, p) in grid.iter_mut().zip(self.0.iter()) {
+            if p.len() != 1 {
+                return None;
+            }
+            *d = p.n();
+        }
+        Some(grid)
+    }
+
+    /// Removes `digit` from the possible values of `cell`, propagating
+    /// the constraints.  Returns whether it was there, so nothing
+    /// happens for a `digit` that is not between 1 and 9.  On a
+    /// contradiction the cell is left without possible values, and
+    /// the logical solver stops there.
+    ///
+    /// Panics if `cell` is not below 81.
+    pub fn remove(&mut self, digit: u8, cell: usize) -> bool {
+        assert!(cell < 81, "{} is not a cell", cell);
+        if !(1..=9).contains(&digit) || !self.0[cell].contains(digit) {
+            return false;
+        }
+        match self.eliminate(digit, cell) {
+            Ok(values) => *self = values,
+            Err(_) => self.0[cell] = Possible(0),
+        }
+        true
+    }
+
+    fn new() -> Self {
+        Values([Possible::new(); 81])
+    }
+
+    /// Builds the possible values for a grid, propagating the
+    /// constraints of every non-empty cell.  Returns `None` if the
+    /// grid is already contradictory.
+    fn from_grid(grid: &[u8; 81]) -> Option<Self> {
+        Values::from_grid_explained(grid).ok()
+    }
+
+    /// Same as `from_grid`, but telling the first contradiction found
+    fn from_grid_explained(grid: &[u8; 81]) -> Result<Self, Explanation> {
+        let mut values = Values::new();
+        for (i, &v) in grid.iter().enumerate().filter(|(_, &v)| v != 0) {
+            values = values.assign(v, i)?;
+        }
+        Ok(values)
+    }
+
+    fn search(self, search: &mut Search, depth: u32) -> Option<Self> {
+        if self.0.iter().any(Possible::is_empty) {
+            // A cell without possible values can't be solved, and
+            // picking it below as the most constrained cell would be
+            // wrong
+            return None;
+        }
+
+        if self.0.iter().all(|p| p.len() == 1) {
+            // Already solved
+            return Some(self);
+        }
+
+        if depth > search.max_depth {
+            search.aborted = true;
+            return None;
+        }
+
+        let cell = search.pick(&self);
+
+        // Return the first found solution (if any) while trying to assign
+        // the possible values for that cell
+        self.0[cell]
+            .values()
+            .filter_map(|n| {
+                if search.should_stop() {
+                    // No point in trying the rest of the values
+                    return None;
+                }
+                search.stats.guesses += 1;
+                if search.progress.is_some() && search.stats.guesses % PROGRESS_EVERY == 0 {
+                    search.report(&self);
+                }
+                trace!("guessing {} in cell {} at depth {}", n, cell, depth);
+                match self.assign(n, cell) {
+                    Ok(values) => values.search(search, depth + 1),
+                    Err(_) => {
+                        trace!("contradiction after {} in cell {}", n, cell);
+                        None
+                    }
+                }
+            })
+            .next()
+    }
+
+    /// Same as `search`, but the pending guesses are kept in an
+    /// explicit stack instead of the call stack.  Tries the values in
+    /// the same order, so it finds the same solution.
+    ///
+    /// `stack` is cleared first, it is only taken to reuse its memory.
+    fn search_iterative(self, search: &mut Search, stack: &mut Vec<Guess>) -> Option<Self> {
+        stack.clear();
+        let mut next = Some(self);
+
+        loop {
+            if let Some(values) = next.take() {
+                if values.0.iter().any(Possible::is_empty) {
+                    // Contradiction, backtrack
+                } else if values.0.iter().all(|p| p.len() == 1) {
+                    return Some(values);
+                } else if stack.len() as u32 > search.max_depth {
+                    search.aborted = true;
+                    return None;
+                } else {
+                    let cell = search.pick(&values);
+                    let pending = values.0[cell];
+                    stack.push((values, cell, pending));
+                }
+            }
+
+            // An empty stack means we ran out of options
+            let (values, cell, pending) = stack.last_mut()?;
+            let n = pending.n();
+            if n == 0 {
+                stack.pop();
+                continue;
+            }
+
+            if search.should_stop() {
+                return None;
+            }
+            *pending = pending.remove(n);
+            search.stats.guesses += 1;
+            next = values.assign(n, *cell).ok();
+        }
+    }
+
+    /// Counts the solutions reachable from this state, but stops as
+    /// soon as `limit` of them are found.
+    fn count_solutions(self, limit: usize) -> usize {
+        if self.0.iter().any(Possible::is_empty) {
+            return 0;
+        }
+
+        if self.0.iter().all(|p| p.len() == 1) {
+            return 1;
+        }
+
+        let cell = self.most_constrained();
+        let mut count = 0;
+        for n in self.0[cell].values() {
+            if count >= limit {
+                break;
+            }
+            if let Ok(values) = self.assign(n, cell) {
+                count += values.count_solutions(limit - count);
+            }
+        }
+        count
+    }
+
+    /// Same search as `count_solutions`, but appending the solutions
+    /// to `buf` instead of just counting them.
+    fn solutions_into(self, buf: &mut Vec<[u8; 81]>, limit: usize) -> usize {
+        if self.0.iter().any(Possible::is_empty) {
+            return 0;
+        }
+
+        if let Some(grid) = self.solved_grid() {
+            buf.push(grid);
+            return 1;
+        }
+
+        let cell = self.most_constrained();
+        let mut count = 0;
+        for n in self.0[cell].values() {
+            if count >= limit {
+                break;
+            }
+            if let Ok(values) = self.assign(n, cell) {
+                count += values.solutions_into(buf, limit - count);
+            }
+        }
+        count
+    }
+
+    /// Find the first square with the least options.
+    /// This way the probability to correctly "guess" is higher
+    /// If we later find that there was a contradiction, we removed
+    /// the most of we can of the possibilities.
+    ///
+    /// Ties go to the lowest index, as `(len, cell)` pairs compare by
+    /// length first and cell second.  The search order, and so the
+    /// statistics and the progress reports, rely on it.
+    fn most_constrained(&self) -> usize {
+        // We can unwrap safely because the search only calls us when
+        // at least 1 such square exists
+        let (_, cell) = self
+            .0
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.len() > 1)
+            .map(|(i, p)| (p.len(), i))
+            .min()
+            .unwrap();
+        cell
+    }
+
+    /// Removes every other possible value from `cell`, propagating the
+    /// constraints.  Fails on the first contradiction found.
+    ///
+    /// Like `eliminate` it takes a digit from 1 to 9 and a cell below
+    /// 81, the public methods check them before getting here.
+    fn assign(self, digit: u8, cell: usize) -> Result<Self, Explanation> {
+        debug_assert!((1..=9).contains(&digit) && cell < 81);
+        let mut values = self;
+
+        // Eliminates all the other possibilities from this cell
+        for other_digit in self.0[cell].values().filter(|&d| d != digit) {
+            values = values.eliminate(other_digit, cell)?
+        }
+        Ok(values)
+    }
+
+    fn eliminate(self, digit: u8, cell: usize) -> Result<Self, Explanation> {
+        debug_assert!((1..=9).contains(&digit) && cell < 81);
+        let mut possibles = self.0[cell];
+
+        if !possibles.contains(digit) {
+            // Was already removed
+            return Ok(self);
+        }
+
+        possibles = possibles.remove(digit);
+
+        let mut values = self;
+        values.0[cell] = possibles;
+
+        match possibles.len() {
+            0 => {
+                // No possible values left: contradiction
+                return Err(Explanation::NoCandidates { cell });
+            }
+            1 => {
+                // If only one possibility left, eliminate it as a possibility
+                // from all its peers
+                let d = possibles.n();
+                for peer in Sudoku::peers(cell as u8) {
+                    values = values.eliminate(d, peer as usize)?
+                }
+            }
+            _ => {}
+        }
+
+        // Check if for any unit, this digit can only appear in one
+        // cell, if so, assign it to that cell
+        for (kind, unit) in Sudoku::units(cell as u8).iter().enumerate() {
+            // We only care about having none, one or more places
+            let mut places_for_d = unit
+                .iter()
+                .filter(|&&p| values.0[p as usize].contains(digit));
+
+            match (places_for_d.next(), places_for_d.next()) {
+                (None, _) => {
+                    let unit = Unit::containing(kind, cell);
+                    return Err(Explanation::NoPlace { digit, unit });
+                }
+                (Some(&place), None) => {
+                    values = values.assign(digit, place as usize)?;
+                }
+                _ => {}
+            };
+        }
+
+        Ok(values)
+    }
+}
+
+/// `SolveStats` gathers some numbers about the work done by the
+/// solver, useful to tell apart easy puzzles from hard ones.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct SolveStats {
+    /// How many values were tried on cells with more than one
+    /// possibility.  Zero means constraint propagation was enough.
+    pub guesses: usize,
+}
+
+/// How many solutions a puzzle has, as far as most people care, see
+/// `Sudoku::solution_class`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SolutionClass {
+    None,
+    Unique,
+    /// Two or more
+    Multiple,
+}
+
+/// What makes a grid unsolvable, see `Sudoku::why_unsolvable`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Explanation {
+    /// No digit can go in this cell
+    NoCandidates { cell: usize },
+    /// No cell of this unit can have `digit`
+    NoPlace { digit: u8, unit: Unit },
+    /// Propagating the constraints finds nothing wrong, but every
+    /// guess ends in a contradiction
+    NoSolution,
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Explanation::NoCandidates { cell } => write!(f, "no digit can go in cell {}", cell),
+            Explanation::NoPlace { digit, unit } => {
+                write!(f, "no place for a {} in {}", digit, unit)
+            }
+            Explanation::NoSolution => write!(f, "every guess leads to a contradiction"),
+        }
+    }
+}
+
+/// A row, column or square, numbered from 0 top to bottom and left to
+/// right.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Unit {
+    Row(u8),
+    Column(u8),
+    Square(u8),
+}
+
+impl Unit {
+    /// The unit of `cell` at `kind` in `Sudoku::units`
+    fn containing(kind: usize, cell: usize) -> Unit {
+        let (row, column) = ((cell / 9) as u8, (cell % 9) as u8);
+        match kind {
+            0 => Unit::Row(row),
+            1 => Unit::Column(column),
+            _ => Unit::Square(row / 3 * 3 + column / 3),
+        }
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Unit::Row(row) => write!(f, "row {}", row),
+            Unit::Column(column) => write!(f, "column {}", column),
+            Unit::Square(square) => write!(f, "square {}", square),
+        }
+    }
+}
+
+/// How the search picks the cell to guess on, see
+/// `Sudoku::solve_with_heuristic`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Heuristic {
+    /// The cell with the fewest possible values, the lowest one on
+    /// ties.  What `solve` uses.
+    MostConstrained,
+    /// The first cell with more than one possible value, row by row
+    FirstEmpty,
+    /// One of the cells with the fewest possible values, at random.
+    /// The random numbers come from `Solver::with_rng`, or a fixed
+    /// seed, so the search is still repeatable.
+    Random,
+}
+
+/// How far `Sudoku::solve_with_progress` got.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SolveProgress {
+    /// Cells with a single possible value in the grid being tried
+    pub cells_solved: usize,
+    /// Values tried so far on cells with more than one possibility
+    pub guesses: usize,
+}
+
+impl SolveStats {
+    pub fn difficulty(&self) -> Difficulty {
+        match self.guesses {
+            0 => Difficulty::Easy,
+            1..=5 => Difficulty::Medium,
+            6..=50 => Difficulty::Hard,
+            _ => Difficulty::Fiendish,
+        }
+    }
+}
+
+/// Coarse rating of a puzzle, ordered from easiest to hardest.
+/// It is derived from the amount of guessing the search needs.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+    Fiendish,
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard",
+            Difficulty::Fiendish => "fiendish",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// `Sudoku` contains a sudoku puzzle.
+/// Can parse from strings to puzzles and display itself.
+/// When calling solve, leverages to `Values::search`.
+///
+/// It also remembers the moves played on it with `play`, to undo them:
+/// two puzzles with the same digits are only equal if they were played
+/// the same.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sudoku([u8; 81], History);
+
+impl Sudoku {
+    /// A grid with all the cells empty.
+    pub fn empty() -> Self {
+        Sudoku([0; 81], History::default())
+    }
+
+    /// Solves the puzzle in place.  Returns false if there is no
+    /// solution, leaving the puzzle untouched.
+    pub fn solve(&mut self) -> bool {
+        self.solve_stats().is_some()
+    }
+
+    /// Like `solve`, but returns the statistics of the search when a
+    /// solution is found.
+    pub fn solve_stats(&mut self) -> Option<SolveStats> {
+        let mut search = Search::new();
+        if self.solve_with(&mut search) {
+            Some(search.stats)
+        } else {
+            None
+        }
+    }
+
+    /// How many guesses `solve` makes on this puzzle, whether it has a
+    /// solution or not.  The search is deterministic, so it is always
+    /// the same number for the same puzzle, unlike the time it takes.
+    pub fn backtrack_count(&self) -> usize {
+        let mut search = Search::new();
+        self.clone().solve_with(&mut search);
+        search.stats.guesses
+    }
+
+    /// Like `solve_stats`, but picking the cells to guess on with
+    /// `heuristic`.  All of them find a solution if there is one, but
+    /// not necessarily the same one when there are several.
+    pub fn solve_with_heuristic(&mut self, heuristic: Heuristic) -> Option<SolveStats> {
+        let result = Solver::new().with_heuristic(heuristic).solve(self);
+        *self = result.solution?;
+        Some(result.stats)
+    }
+
+    /// Like `solve`, but calls `progress` every now and then while
+    /// searching, and once more when a solution is found.  Puzzles that
+    /// need no guesses only get that last call.
+    pub fn solve_with_progress(&mut self, mut progress: impl FnMut(SolveProgress)) -> bool {
+        let mut search = Search {
+            progress: Some(&mut progress),
+            ..Search::new()
+        };
+        if !self.solve_with(&mut search) {
+            return false;
+        }
+        let guesses = search.stats.guesses;
+        progress(SolveProgress {
+            cells_solved: 81,
+            guesses,
+        });
+        true
+    }
+
+    /// Like `solve`, but gives up if the search needs more than `max`
+    /// nested guesses.  Returns `None` when it gave up, leaving the
+    /// puzzle untouched, and whether it was solved otherwise.
+    pub fn solve_max_depth(&mut self, max: u32) -> Option<bool> {
+        let mut search = Search {
+            max_depth: max,
+            ..Search::new()
+        };
+        let solved = self.solve_with(&mut search);
+        if search.aborted {
+            None
+        } else {
+            Some(solved)
+        }
+    }
+
+    /// Like `solve`, but gives up with `SudokuError::Cancelled` once
+    /// `cancel` is set, leaving the puzzle untouched.  The flag is
+    /// checked before every guess, so a cancelled solve only finishes
+    /// propagating the last one, well under a millisecond.  Returns
+    /// whether it was solved otherwise.
+    pub fn solve_cancellable(&mut self, cancel: &AtomicBool) -> Result<bool, SudokuError> {
+        let mut search = Search {
+            cancel: Some(cancel),
+            ..Search::new()
+        };
+        let solved = self.solve_with(&mut search);
+        if search.aborted {
+            Err(SudokuError::Cancelled)
+        } else {
+            Ok(solved)
+        }
+    }
+
+    /// Solves the puzzle on tokio's blocking thread pool, so the
+    /// executor threads are free meanwhile.
+    ///
+    /// The solve starts when the future is first polled, which must
+    /// happen within a tokio runtime, and runs on its own thread from
+    /// then on.  Resolves to `None` if there is no solution.
+    ///
+    /// Dropping the future doesn't stop the solve, use
+    /// `solve_cancellable` for searches that may have to be stopped.
+    #[cfg(feature = "async")]
+    pub async fn solve_async(self) -> Option<Sudoku> {
+        let handle = tokio::task::spawn_blocking(move || self.into_solution());
+
+        match handle.await {
+            Ok(solution) => solution,
+            Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+            // The runtime is shutting down
+            Err(_) => None,
+        }
+    }
+
+    fn into_solution(mut self) -> Option<Sudoku> {
+        if self.solve() {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    /// Sets `cell` to `digit` (0 to empty it) and solves the puzzle
+    /// again, returning the new solution.  Only the changed cell is
+    /// written to the puzzle, so the clues can still be told apart from
+    /// the rest.  If the change leaves no solution, or the cell or the
+    /// digit are out of range, the puzzle is left as it was and `None`
+    /// is returned.
+    ///
+    /// The previous solution is not reused: changing a clue can undo
+    /// any of the deductions made from it.
+    pub fn resolve_after_change(&mut self, cell: usize, digit: u8) -> Option<Sudoku> {
+        if cell >= 81 || digit > 9 {
+            return None;
+        }
+
+        let mut solution = self.clone();
+        solution.0[cell] = digit;
+        if !solution.solve() {
+            return None;
+        }
+        self.0[cell] = digit;
+        Some(solution)
+    }
+
+    /// Fills in the empty `cell` with its digit in the solution, and
+    /// returns it, leaving the other cells as they were.  `None` if the
+    /// cell is not empty or out of range, or the puzzle has no
+    /// solution.  With several solutions the digit comes from the one
+    /// `solve` finds.
+    ///
+    /// Unlike the hints from `naked_singles`, there is always a digit
+    /// to reveal, even if no logic leads to it yet.
+    pub fn reveal(&mut self, cell: usize) -> Option<u8> {
+        if cell >= 81 || self.0[cell] != 0 {
+            return None;
+        }
+
+        let mut solution = self.clone();
+        if !solution.solve() {
+            return None;
+        }
+        self.0[cell] = solution.0[cell];
+        Some(self.0[cell])
+    }
+
+    /// A copy of the puzzle with `digit` as one more clue in `cell`,
+    /// to make a puzzle out of the solution of another.  The clue is
+    /// checked like a move of `play`, and may replace a digit played
+    /// there.  Only the peers are checked: the new puzzle may have no
+    /// solution even if it succeeds.  Giving a clue again is fine.
+    ///
+    /// Fails with `SudokuError::Move` and why `play` would refuse it,
+    /// or `MoveError::InvalidDigit` for a 0.
+    pub fn with_additional_given(&self, cell: usize, digit: u8) -> Result<Sudoku, SudokuError> {
+        if digit == 0 {
+            return Err(MoveError::InvalidDigit(digit).into());
+        }
+        if cell < 81 && self.is_given(cell) && self.0[cell] == digit {
+            return Ok(self.clone());
+        }
+        self.check_move(cell, digit)?;
+        let mut sudoku = self.clone();
+        sudoku.1.forget(cell);
+        sudoku.0[cell] = digit;
+        Ok(sudoku)
+    }
+
+    /// Same as `solve` but does not use recursion to backtrack, so
+    /// the stack usage does not depend on the puzzle.
+    pub fn solve_iterative(&mut self) -> bool {
+        Solver::new().solve_into(self)
+    }
+
+    /// Solves the puzzle without guessing, only with constraint
+    /// propagation and the human techniques (see `Technique`).  Returns
+    /// whether the grid was completed.  When the techniques get stuck
+    /// the puzzle keeps the cells they decided and the rest stay empty.
+    /// Only the digits are kept: what the techniques ruled out of the
+    /// empty cells is lost, and `candidates_display` only shows what
+    /// propagation rules out again.  A puzzle with no solution is an
+    /// error, and is left untouched.
+    ///
+    /// The unique rectangle (see `Technique::UniqueRectangle`) is only
+    /// tried once the puzzle is known to have a single solution.
+    pub fn solve_logical(&mut self) -> Result<bool, SudokuError> {
+        if self.is_unique() {
+            self.solve_logical_using(&Technique::UNIQUE_PIPELINE)
+        } else {
+            self.solve_logical_using(&Technique::PIPELINE)
+        }
+    }
+
+    /// Like `solve_logical`, but only with `strategies`, tried in that
+    /// order.  `Technique::pipeline` has the built-in ones.
+    pub fn solve_logical_with(
+        &mut self,
+        strategies: &[Box<dyn Strategy>],
+    ) -> Result<bool, SudokuError> {
+        self.solve_logical_using(strategies)
+    }
+
+    /// The solution using only `techniques`, and guessing only if
+    /// `Technique::Guess` is one of them.  `None` if they are not enough
+    /// to finish the puzzle, or if it has no solution.  Singles are
+    /// always used, they come with the constraint propagation.
+    pub fn solve_with_strategies(&self, techniques: &[Technique]) -> Option<Sudoku> {
+        Solver::new()
+            .with_techniques(techniques)
+            .solve(self)
+            .solution
+    }
+
+    fn solve_logical_using<S: Strategy>(&mut self, strategies: &[S]) -> Result<bool, SudokuError> {
+        let mut values = Values::from_grid(&self.0).ok_or(SudokuError::Contradiction)?;
+        let solved = techniques::solve_logical(&mut values, strategies, &mut Vec::new());
+        if values.0.iter().any(Possible::is_empty) {
+            return Err(SudokuError::Contradiction);
+        }
+
+        for (cell, p) in self.0.iter_mut().zip(values.0.iter()) {
+            if p.len() == 1 {
+                *cell = p.n();
+            }
+        }
+        Ok(solved)
+    }
+
+    fn solve_with(&mut self, search: &mut Search) -> bool {
+        debug!("solving {}", self.to_line());
+        let solution = Values::from_grid(&self.0).and_then(|values| Sudoku::finish(values, search));
+        let solved = solution.is_some();
+        if let Some(solution) = solution {
+            *self = solution;
+        }
+        debug!("solved: {} after {} guesses", solved, search.stats.guesses);
+        solved
+    }
+
+    /// Finishes solving from `values` instead of from the clues, for
+    /// instance after taking away some possible values with
+    /// `Values::remove`.  Returns `None` if there is no solution left.
+    pub fn solve_from(values: Values) -> Option<Sudoku> {
+        Sudoku::finish(values, &mut Search::new())
+    }
+
+    /// The solution `search` finds from `values`, if it really is one
+    fn finish(values: Values, search: &mut Search) -> Option<Sudoku> {
+        let grid = values.search(search, 0)?.solved_grid()?;
+        let solution = Sudoku(grid, History::default());
+        // Don't trust the propagation, nor values that were tampered
+        // with: check the rules again
+        Some(solution).filter(Sudoku::is_solved)
+    }
+
+    /// What makes the puzzle unsolvable, or `None` if it has a
+    /// solution.  The contradiction reported is the first one the
+    /// propagation of the clues runs into, which may be far from a
+    /// wrong clue; if the clues propagate fine but there is still no
+    /// solution, it is `Explanation::NoSolution`.
+    pub fn why_unsolvable(&self) -> Option<Explanation> {
+        match Values::from_grid_explained(&self.0) {
+            Err(explanation) => Some(explanation),
+            Ok(values) if values.count_solutions(1) == 0 => Some(Explanation::NoSolution),
+            Ok(_) => None,
+        }
+    }
+
+    /// The possible values of every cell after constraint propagation,
+    /// to narrow them down further before `Sudoku::solve_from`.
+    /// `None` if the puzzle is contradictory.
+    pub fn values(&self) -> Option<Values> {
+        Values::from_grid(&self.0)
+    }
+
+    /// The 81 cells in a single line, row by row, with a `.` for the
+    /// empty ones.  It can be parsed back with `try_from`.
+    pub fn to_line(&self) -> String {
+        self.0
+            .iter()
+            .map(|&d| if d == 0 { '.' } else { (b'0' + d) as char })
+            .collect()
+    }
+
+    /// Like `to_line`, but with a `0` for the empty cells, as some
+    /// formats and solvers that don't take `.`s expect.
+    pub fn to_line_zero(&self) -> String {
+        self.0.iter().map(|&d| (b'0' + d) as char).collect()
+    }
+
+    /// Like `to_line`, but the digits of the cells that are not in
+    /// `givens` are written as the letters `a` for 1 to `i` for 9, so a
+    /// solution keeps track of the clues of its puzzle.  Use
+    /// `puzzle.givens()` for the mask, and `Sudoku::parse_annotated` to
+    /// read it back.
+    pub fn to_line_annotated(&self, givens: &[bool; 81]) -> String {
+        self.0
+            .iter()
+            .zip(givens.iter())
+            .map(|(&d, &given)| match d {
+                0 => '.',
+                _ if given => (b'0' + d) as char,
+                _ => (b'a' + d - 1) as char,
+            })
+            .collect()
+    }
+
+    /// Which cells are clues, see `is_given`.
+    pub fn givens(&self) -> [bool; 81] {
+        std::array::from_fn(|cell| self.is_given(cell))
+    }
+
+    /// The cells that hold something different in `other`, with the
+    /// value here and the one in `other`.  Diffing a puzzle against its
+    /// solution gives the cells filled by the solver.
+    pub fn diff(&self, other: &Sudoku) -> Vec<(usize, u8, u8)> {
+        self.0
+            .iter()
+            .zip(other.0.iter())
+            .enumerate()
+            .filter(|(_, (a, b))| a != b)
+            .map(|(i, (&a, &b))| (i, a, b))
+            .collect()
+    }
+
+    /// Number of non-empty cells in every row, column and square, in
+    /// that order.  Rows go top to bottom, columns left to right and
+    /// squares row by row.
+    pub fn clue_distribution(&self) -> ([u8; 9], [u8; 9], [u8; 9]) {
+        let clues =
+            |cells: [u8; 9]| cells.iter().filter(|&&c| self.0[c as usize] != 0).count() as u8;
+        let (mut rows, mut columns, mut squares) = ([0; 9], [0; 9], [0; 9]);
+        for i in 0..9 {
+            rows[i as usize] = clues(Sudoku::row_cells(i));
+            columns[i as usize] = clues(Sudoku::column_cells(i));
+            squares[i as usize] = clues(Sudoku::box_cells(i));
+        }
+        (rows, columns, squares)
+    }
+
+    /// A hypersudoku, or windoku: a 9x9 `Board` whose four windows,
+    /// the 3x3 boxes with their top left corners at rows and columns 1
+    /// and 5, must also hold every digit.  Fails like
+    /// `Board::rectangular` if `grid` doesn't have 81 cells.
+    pub fn hyper(grid: Vec<u8>) -> Result<Board, SudokuError> {
+        Board::hyper(grid)
+    }
+
+    /// How many times every digit appears, from 1 to 9.  All of them
+    /// are 9 in a solved grid.
+    pub fn digit_counts(&self) -> [u8; 9] {
+        let mut counts = [0; 9];
+        for &d in self.0.iter().filter(|&&d| d != 0) {
+            counts[d as usize - 1] += 1;
+        }
+        counts
+    }
+
+    /// Whether every cell is filled and no row, column or square has
+    /// the same digit twice.
+    pub fn is_solved(&self) -> bool {
+        (0..9).all(|i| {
+            let units = [
+                Sudoku::row_cells(i),
+                Sudoku::column_cells(i),
+                Sudoku::box_cells(i),
+            ];
+            units.iter().all(|unit| {
+                // An empty cell sets bit 0
+                let seen = unit
+                    .iter()
+                    .fold(0u16, |seen, &c| seen | 1 << self.0[c as usize]);
+                seen == 0b11_1111_1110
+            })
+        })
+    }
+
+    /// Whether the puzzle has one solution and only one.
+    pub fn is_unique(&self) -> bool {
+        // No puzzle with less than 17 clues has a unique solution, as
+        // shown by McGuire, Tugemann and Civario in "There is no 16-clue
+        // Sudoku" (2012), so there is no need to search those
+        self.clue_count() >= 17 && self.solution_count_capped(2) == 1
+    }
+
+    /// Appends the solutions of the puzzle to `buf`, cells row by row,
+    /// stopping after `limit` of them.  Returns how many were added.
+    pub fn solve_all_into(&self, buf: &mut Vec<[u8; 81]>, limit: usize) -> usize {
+        match Values::from_grid(&self.0) {
+            Some(values) if limit > 0 => values.solutions_into(buf, limit),
+            _ => 0,
+        }
+    }
+
+    /// Whether the puzzle has no solution, one, or more.  The search
+    /// stops at the second one, so it is as fast as `is_unique`.
+    pub fn solution_class(&self) -> SolutionClass {
+        match self.solution_count_capped(2) {
+            0 => SolutionClass::None,
+            1 => SolutionClass::Unique,
+            _ => SolutionClass::Multiple,
+        }
+    }
+
+    /// Number of non-empty cells.
+    pub fn clue_count(&self) -> usize {
+        self.0.iter().filter(|&&d| d != 0).count()
+    }
+
+    /// Number of solutions of the puzzle, counting no further than
+    /// `cap`: the search stops as soon as it finds that many.
+    pub fn solution_count_capped(&self, cap: usize) -> usize {
+        match Values::from_grid(&self.0) {
+            Some(values) if cap > 0 => values.count_solutions(cap),
+            _ => 0,
+        }
+    }
+
+    /// For a puzzle without solution, a minimal set of its clues that
+    /// already has none: taking any of them away leaves a solvable
+    /// grid.  Clues are tried in order and dropped while the rest stays
+    /// unsolvable, so it is one such set, not necessarily the smallest.
+    /// Empty if the puzzle can be solved.
+    pub fn minimal_unsat_core(&self) -> Vec<usize> {
+        if self.solution_count_capped(1) != 0 {
+            return Vec::new();
+        }
+        let mut core = self.clone();
+        for cell in 0..81 {
+            let digit = core.0[cell];
+            core.0[cell] = 0;
+            if core.solution_count_capped(1) != 0 {
+                core.0[cell] = digit;
+            }
+        }
+        (0..81).filter(|&cell| core.0[cell] != 0).collect()
+    }
+
+    /// Rates the puzzle by solving a copy of it.  Returns `None` if
+    /// the puzzle has no solution.
+    pub fn difficulty(&self) -> Option<Difficulty> {
+        self.clone().solve_stats().map(|stats| stats.difficulty())
+    }
+
+    /// A finer rating than `difficulty`, the higher the harder, to sort
+    /// puzzles of the same difficulty.
+    ///
+    /// The puzzle is solved with the human techniques, and only when
+    /// they get stuck with guesses.  Every empty cell adds the weight
+    /// of `Technique::Singles`, as all of them end up being a single,
+    /// and every use of another technique and every guess add their
+    /// weight too (see `Technique::weight`).  Unsolvable puzzles score
+    /// `u32::MAX`.
+    pub fn score(&self) -> u32 {
+        let mut values = match Values::from_grid(&self.0) {
+            Some(values) => values,
+            None => return u32::MAX,
+        };
+        let empty = 81 - self.clue_count() as u32;
+        let mut score = empty * Technique::Singles.weight();
+
+        let mut steps = Vec::new();
+        if !techniques::solve_logical(&mut values, &Technique::PIPELINE, &mut steps) {
+            let mut search = Search::new();
+            if values.search(&mut search, 0).is_none() {
+                return u32::MAX;
+            }
+            score += search.stats.guesses as u32 * Technique::Guess.weight();
+        }
+        score
+            + steps
+                .iter()
+                .map(|&i| Technique::PIPELINE[i].weight())
+                .sum::<u32>()
+    }
+
+    /// The most difficult technique needed to solve the puzzle with
+    /// the human techniques, `Technique::Singles` if nothing else is.
+    /// Puzzles they can't finish need a `Technique::Guess`, unsolvable
+    /// ones included.
+    pub fn hardest_technique(&self) -> Technique {
+        let mut values = match Values::from_grid(&self.0) {
+            Some(values) => values,
+            None => return Technique::Guess,
+        };
+        let mut steps = Vec::new();
+        if !techniques::solve_logical(&mut values, &Technique::PIPELINE, &mut steps) {
+            return Technique::Guess;
+        }
+        steps
+            .iter()
+            .map(|&i| Technique::PIPELINE[i])
+            .max()
+            .unwrap_or(Technique::Singles)
+    }
+
+    /// Number of possible values of every cell after constraint
+    /// propagation only, 1 for the decided ones.  If the puzzle is
+    /// contradictory every count is 0.
+    pub fn candidate_counts(&self) -> [u8; 81] {
+        let mut counts = [0; 81];
+        for (count, p) in counts.iter_mut().zip(self.candidates().0) {
+            *count = p.len() as u8;
+        }
+        counts
+    }
+
+    /// The empty cells that constraint propagation decides, and their
+    /// digit, by cell.  Empty if the puzzle is contradictory.
+    pub fn naked_singles(&self) -> Vec<(usize, u8)> {
+        let values = self.candidates();
+        (0..81)
+            .filter(|&cell| self.0[cell] == 0 && values.0[cell].len() == 1)
+            .map(|cell| (cell, values.0[cell].n()))
+            .collect()
+    }
+
+    /// The empty cells where `digit` can still go after constraint
+    /// propagation, by cell.  Empty if the puzzle is contradictory, or
+    /// if `digit` is already in every unit.  Panics unless `digit` is
+    /// between 1 and 9.
+    pub fn placements(&self, digit: u8) -> Vec<usize> {
+        assert!((1..=9).contains(&digit), "{} is not a digit", digit);
+        let values = self.candidates();
+        (0..81)
+            .filter(|&cell| self.0[cell] == 0 && values.0[cell].contains(digit))
+            .collect()
+    }
+
+    /// Possible values after constraint propagation, none at all if
+    /// the puzzle is contradictory
+    fn candidates(&self) -> Values {
+        Values::from_grid(&self.0).unwrap_or(Values([Possible(0); 81]))
+    }
+
+    /// Draws the grid like `Display` does, but in the style chosen by
+    /// `options`.
+    pub fn display_with(&self, options: DisplayOptions) -> String {
+        let digits: Vec<String> = self
+            .0
+            .iter()
+            .map(|&n| {
+                if n == 0 {
+                    options.blank.to_string()
+                } else {
+                    n.to_string()
+                }
+            })
+            .collect();
+        // The separators are as wide as the boxes, whatever it takes to
+        // draw a cell
+        let widest = digits.iter().map(|d| d.chars().count()).max().unwrap_or(1);
+        let default_width = if options.compact { widest } else { widest + 1 };
+        let width = options.cell_width.unwrap_or(default_width).max(widest);
+        let pad = " ".repeat(width - widest);
+        let line = ("+".to_string() + &"-".repeat(3 * width)).repeat(3) + "+\n";
+
+        let mut buffer = String::new();
+        for (r, row) in digits.chunks(9).enumerate() {
+            if options.borders && r % 3 == 0 {
+                buffer.push_str(&line);
+            }
+            if options.borders {
+                for (c, d) in row.iter().enumerate() {
+                    if c % 3 == 0 {
+                        buffer.push('|');
+                    }
+                    buffer.push_str(&format!("{:^1$}", d, width));
+                }
+                buffer.push('|');
+            } else {
+                buffer.push_str(&row.join(&pad));
+            }
+            buffer.push('\n');
+        }
+        if options.borders {
+            buffer.push_str(&line);
+        }
+        buffer
+    }
+
+    /// Renders the possible values of every cell after constraint
+    /// propagation only, without guessing.  Each cell is drawn as a
+    /// 3x3 block with its candidates in place and a `.` for the
+    /// discarded ones, except decided cells that only show their
+    /// digit.  If the puzzle is contradictory no cell has candidates.
+    pub fn candidates_display(&self) -> String {
+        let values = self.candidates();
+
+        let border = "+-------------+-------------+-------------+\n";
+        let spacer = "|             |             |             |\n";
+        let mut buffer = String::new();
+        for row in 0..9 {
+            if row % 3 == 0 {
+                buffer.push_str(border);
+            } else {
+                buffer.push_str(spacer);
+            }
+
+            // Every row of cells takes three lines of text
+            for line in 0..3u8 {
+                for col in 0..9 {
+                    buffer.push_str(if col % 3 == 0 { "| " } else { " " });
+
+                    let p = values.0[row * 9 + col];
+                    for d in (3 * line + 1)..=(3 * line + 3) {
+                        let c = if p.len() == 1 {
+                            if line == 1 && d == 5 {
+                                (b'0' + p.n()) as char
+                            } else {
+                                ' '
+                            }
+                        } else if p.contains(d) {
+                            (b'0' + d) as char
+                        } else {
+                            '.'
+                        };
+                        buffer.push(c);
+                    }
+
+                    if col == 8 {
+                        buffer.push_str(" |\n");
+                    } else if col % 3 == 2 {
+                        buffer.push(' ');
+                    }
+                }
+            }
+        }
+        buffer.push_str(border);
+        buffer
+    }
+
+    /// The cells of row `row`, from 0 at the top, left to right.
+    pub fn row_cells(row: u8) -> [u8; 9] {
+        std::array::from_fn(|c| row * 9 + c as u8)
+    }
+
+    /// The cells of column `column`, from 0 on the left, top to bottom.
+    pub fn column_cells(column: u8) -> [u8; 9] {
+        std::array::from_fn(|r| r as u8 * 9 + column)
+    }
+
+    /// The cells of box `box_idx`, numbered row by row from 0 at the
+    /// top left, and also row by row inside the box.
+    pub fn box_cells(box_idx: u8) -> [u8; 9] {
+        // Top left corner: first row of the band and first column of
+        // the stack
+        let corner = box_idx / 3 * 27 + box_idx % 3 * 3;
+        std::array::from_fn(|n| corner + n as u8 / 3 * 9 + n as u8 % 3)
+    }
+
+    /// Iterator containing the cell indices of the whole row in which
+    /// `cell` is, `cell` included
+    fn row_full(cell: u8) -> impl Iterator<Item = u8> {
+        IntoIterator::into_iter(Sudoku::row_cells(cell / 9))
+    }
+
+    /// Iterator containing the cell indices of the whole column in
+    /// which `cell` is, `cell` included
+    fn column_full(cell: u8) -> impl Iterator<Item = u8> {
+        IntoIterator::into_iter(Sudoku::column_cells(cell % 9))
+    }
+
+    /// Iterator containing the cell indices of the whole square in
+    /// which `cell` is, `cell` included
+    fn square_full(cell: u8) -> impl Iterator<Item = u8> {
+        IntoIterator::into_iter(Sudoku::box_cells(cell / 27 * 3 + cell % 9 / 3))
+    }
+
+    /// Iterator containing the cell indices of the row in which
+    /// `cell` is, without `cell` itself: its peers in the row
+    fn row(cell: u8) -> impl Iterator<Item = u8> {
+        Sudoku::row_full(cell).filter(move |&r| r != cell)
+    }
+
+    /// Iterator containing the cell indices of the column in which
+    /// `cell` is, without `cell` itself: its peers in the column
+    fn column(cell: u8) -> impl Iterator<Item = u8> {
+        Sudoku::column_full(cell).filter(move |&c| c != cell)
+    }
+
+    /// Iterator containing the cell indices of the square in which
+    /// `cell` is, without `cell` itself: its peers in the square.
+    fn square(cell: u8) -> impl Iterator<Item = u8> {
+        Sudoku::square_full(cell).filter(move |&t| t != cell)
+    }
+
+    /// The row, column and square of `i`, without `i` itself.  They
+    /// are arrays so the hot path in `Values::eliminate` does not
+    /// allocate.
+    ///
+    /// Cells are numbered row by row, from 0 at the top left to 80 at
+    /// the bottom right, and every unit is in increasing order.  Panics
+    /// if `i` is not below 81.
+    pub fn units(i: u8) -> [[u8; 8]; 3] {
+        assert!(i < 81, "cell {} is outside the grid", i);
+        let mut units = [[0; 8]; 3];
+        for (u, c) in units[0].iter_mut().zip(Sudoku::row(i)) {
+            *u = c;
+        }
+        for (u, c) in units[1].iter_mut().zip(Sudoku::column(i)) {
+            *u = c;
+        }
+        for (u, c) in units[2].iter_mut().zip(Sudoku::square(i)) {
+            *u = c;
+        }
+        units
+    }
+
+    /// The 20 cells that share a unit with `i`, each of them once: its
+    /// row, its column, and the cells of its square that are in
+    /// neither.  Cells are numbered like in `Sudoku::units`.  Panics if
+    /// `i` is not below 81.
+    pub fn peers(i: u8) -> impl Iterator<Item = u8> {
+        assert!(i < 81, "cell {} is outside the grid", i);
+        Sudoku::row(i)
+            .chain(Sudoku::column(i))
+            .chain(Sudoku::square(i).filter(move |&c| c / 9 != i / 9 && c % 9 != i % 9))
+    }
+}
+
+/// Solves every puzzle, returning the solutions in the same order as
+/// the puzzles, and `None` for the unsolvable ones.  With the
+/// `parallel` feature puzzles are spread across rayon's thread pool.
+pub fn solve_batch(puzzles: Vec<Sudoku>) -> Vec<Option<Sudoku>> {
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        puzzles.into_par_iter().map(Sudoku::into_solution).collect()
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    {
+        puzzles.into_iter().map(Sudoku::into_solution).collect()
+    }
+}
+
+/// How `Sudoku::display_with` draws a grid.  The default is the one
+/// `Display` uses.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DisplayOptions {
+    /// No spaces between the cells
+    pub compact: bool,
+    /// Draw the lines around the boxes
+    pub borders: bool,
+    /// What to draw in the empty cells, like `.` or `·`.  The parsers
+    /// only take those two back.
+    pub blank: char,
+    /// How many columns every cell takes, with the digit in the
+    /// middle: 1 is as `compact`, 3 leaves a space on both sides.  By
+    /// default 1 if `compact` and 2 otherwise.  Without borders the
+    /// cells are only separated by the extra columns.
+    pub cell_width: Option<usize>,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        DisplayOptions {
+            compact: false,
+            borders: true,
+            blank: '.',
+            cell_width: None,
+        }
+    }
+}
+
+impl DisplayOptions {
+    /// Borders, but no spaces, for narrow terminals
+    pub fn compact() -> Self {
+        DisplayOptions {
+            compact: true,
+            ..DisplayOptions::default()
+        }
+    }
+
+    /// Only the cells, one row per line
+    pub fn plain() -> Self {
+        DisplayOptions {
+            borders: false,
+            ..DisplayOptions::default()
+        }
+    }
+}
+
+impl fmt::Display for Sudoku {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.display_with(DisplayOptions::default()))
+    }
+}
+
+impl TryFrom<&str> for Sudoku {
+    type Error = SudokuError;
+
+    /// We expect to read 81 grid data between digits and `.`s.
+    /// A dot (`.`), a middle dot (`·`) or a `0` means that that
+    /// particular cell is empty.
+    /// All other non-digit values are ignored, and so is anything
+    /// after the 81st cell (see `Sudoku::parse_strict`).
+    /// If a grid can not be read, an Err is returned.
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Sudoku::parse(value, false)
+    }
+}
+
+impl TryFrom<&[u8]> for Sudoku {
+    type Error = SudokuError;
+
+    /// The 81 cells row by row, with a 0 for the empty ones.  Any other
+    /// length is an error, and so is a value over 9.
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        if value.len() < 81 {
+            return Err(SudokuError::TooFewCells);
+        }
+        let grid = <[u8; 81]>::try_from(value).map_err(|_| SudokuError::TooManyCells)?;
+        Sudoku::from_grid(grid)
+    }
+}
+
+impl TryFrom<Vec<u8>> for Sudoku {
+    type Error = SudokuError;
+
+    /// Same as for `&[u8]`.
+    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
+        Sudoku::try_from(value.as_slice())
+    }
+}
+
+impl From<[[u8; 9]; 9]> for Sudoku {
+    /// The rows top to bottom, every one of them left to right, with a
+    /// 0 for the empty cells, for grids already known to be valid.
+    /// Anything over 9 is read as an empty cell, see
+    /// `Sudoku::from_rows` to get an error instead.
+    fn from(rows: [[u8; 9]; 9]) -> Self {
+        let mut grid = [0; 81];
+        for (cell, &d) in grid.iter_mut().zip(rows.iter().flatten()) {
+            if d <= 9 {
+                *cell = d;
+            }
+        }
+        Sudoku(grid, History::default())
+    }
+}
+
+impl Sudoku {
+    /// Builds a puzzle from its rows, top to bottom, with a 0 for
+    /// the empty cells.
+    pub fn from_rows(rows: [[u8; 9]; 9]) -> Result<Self, SudokuError> {
+        let mut grid = [0u8; 81];
+        for (cells, row) in grid.chunks_mut(9).zip(rows.iter()) {
+            cells.copy_from_slice(row);
+        }
+        Sudoku::from_grid(grid)
+    }
+
+    /// The rows of the puzzle, top to bottom, with a 0 for the empty
+    /// cells.
+    pub fn to_rows(&self) -> [[u8; 9]; 9] {
+        let mut rows = [[0u8; 9]; 9];
+        for (row, cells) in rows.iter_mut().zip(self.0.chunks(9)) {
+            row.copy_from_slice(cells);
+        }
+        rows
+    }
+
+    /// The grid as rows, row 0 at the top and column 0 on the left,
+    /// the same as `to_rows`.  `Sudoku::from` takes it back.
+    pub fn to_grid(&self) -> [[u8; 9]; 9] {
+        self.to_rows()
+    }
+
+    /// The solution as rows, like `to_rows`, leaving the puzzle as it
+    /// is.  `None` if there is no solution, or more than one to choose
+    /// from.
+    pub fn solution_grid(&self) -> Option<[[u8; 9]; 9]> {
+        let mut solutions = Vec::with_capacity(2);
+        if self.solve_all_into(&mut solutions, 2) != 1 {
+            return None;
+        }
+        Some(Sudoku(solutions[0], History::default()).to_rows())
+    }
+
+    /// Checks that every cell holds a digit or a 0
+    fn from_grid(grid: [u8; 81]) -> Result<Self, SudokuError> {
+        match grid.iter().position(|&d| d > 9) {
+            Some(cell) => Err(SudokuError::InvalidDigit {
+                cell,
+                digit: grid[cell],
+            }),
+            None => Ok(Sudoku(grid, History::default())),
+        }
+    }
+
+    /// The 81 cells of the puzzle in `input` as `try_from` reads them,
+    /// digits with a `0` for the empty cells and nothing else, to see
+    /// what was understood of a grid with mixed conventions.  Fails the
+    /// same way `try_from` does.
+    pub fn normalize(input: &str) -> Result<String, SudokuError> {
+        Ok(Sudoku::try_from(input)?.to_line_zero())
+    }
+
+    /// Like `try_from`, but fails with `SudokuError::TooManyCells`
+    /// if there are digits or `.`s after the 81st cell instead of
+    /// ignoring them, so two puzzles stuck together are not taken as
+    /// the first one.
+    pub fn parse_strict(value: &str) -> Result<Self, SudokuError> {
+        Sudoku::parse(value, true)
+    }
+
+    /// Reads 81 numbers separated by commas or whitespace, as exported
+    /// by spreadsheets, with a `0` or a `.` for the empty cells.  Unlike
+    /// `try_from` nothing is ignored: any other token is an error, and
+    /// so are extra cells.
+    pub fn parse_csv(value: &str) -> Result<Self, SudokuError> {
+        let mut grid = [0u8; 81];
+        let mut tokens = value
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|t| !t.is_empty());
+        for (cell, d) in grid.iter_mut().enumerate() {
+            match tokens.next() {
+                None => return Err(SudokuError::TooFewCells),
+                Some(".") | Some("·") => {}
+                Some(token) => {
+                    *d = token
+                        .parse()
+                        .map_err(|_| SudokuError::InvalidToken { cell })?
+                }
+            }
+        }
+        if tokens.next().is_some() {
+            return Err(SudokuError::TooManyCells);
+        }
+        Sudoku::from_grid(grid)
+    }
+
+    /// Reads a line from `to_line_annotated`, returning the grid with
+    /// the letters as digits, and which cells held a digit, the clues.
+    /// Extra cells are an error like in `parse_strict`.
+    pub fn parse_annotated(line: &str) -> Result<(Self, [bool; 81]), SudokuError> {
+        let mut givens = [false; 81];
+        let mut cell = 0;
+        let plain: String = line
+            .chars()
+            .filter_map(|c| {
+                let (c, given) = match c {
+                    'a'..='i' => ((c as u8 - b'a' + b'1') as char, false),
+                    '1'..='9' => (c, true),
+                    '0' | '.' => (c, false),
+                    _ => return None,
+                };
+                if cell < 81 {
+                    givens[cell] = given;
+                }
+                cell += 1;
+                Some(c)
+            })
+            .collect();
+        Ok((Sudoku::parse_strict(&plain)?, givens))
+    }
+
+    fn parse(value: &str, strict: bool) -> Result<Self, SudokuError> {
+        let mut grid = [0u8; 81];
+
+        let mut i = 0;
+        for c in value.chars() {
+            if i > 80 {
+                if !strict {
+                    // No need to read more
+                    break;
+                }
+
+                if c == '.' || c == '·' || c.is_ascii_digit() {
+                    return Err(SudokuError::TooManyCells);
+                }
+                continue;
+            }
+
+            if c == '.' || c == '·' {
+                // We leave the 0 in place and count it as a digit
+                i += 1;
+                continue;
+            }
+
+            if let Some(d) = c.to_digit(10) {
+                // If parsing the digit fails (and is not a `.`), we
+                // ignore it
+                grid[i] = d as u8;
+                i += 1;
+            }
+        }
+
+        if i == 81 {
+            Ok(Sudoku(grid, History::default()))
+        } else {
+            Err(SudokuError::TooFewCells)
+        }
+    }
+}
+
+/// Everything that can go wrong when building or solving a `Sudoku`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SudokuError {
+    /// The input ended before all 81 cells were read
+    TooFewCells,
+    /// There are more than 81 cells in the input
+    TooManyCells,
+    /// A cell holds something other than a digit or a 0
+    InvalidDigit { cell: usize, digit: u8 },
+    /// A cell of a CSV grid is not a number (see `Sudoku::parse_csv`)
+    InvalidToken { cell: usize },
+    /// The cells can't fill a square board with boxes of that size
+    InvalidShape { cells: usize, box_w: u8, box_h: u8 },
+    /// The puzzle has no solution
+    Contradiction,
+    /// There is no such cell on the board
+    InvalidCell { cell: usize },
+    /// A digit can't go in a cell, see `Sudoku::with_additional_given`
+    Move(MoveError),
+    /// The search was cancelled before it finished
+    Cancelled,
+}
+
+impl fmt::Display for SudokuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SudokuError::TooFewCells => write!(f, "malformed grid: less than 81 cells"),
+            SudokuError::TooManyCells => write!(f, "malformed grid: more than 81 cells"),
+            SudokuError::InvalidDigit { cell, digit } => {
+                write!(f, "invalid digit {} in cell {}", digit, cell)
+            }
+            SudokuError::InvalidToken { cell } => write!(f, "cell {} is not a number", cell),
+            SudokuError::InvalidShape {
+                cells,
+                box_w,
+                box_h,
+            } => write!(
+                f,
+                "malformed grid: {} cells can't have {}x{} boxes",
+                cells, box_w, box_h
+            ),
+            SudokuError::Contradiction => write!(f, "the puzzle has no solution"),
+            SudokuError::InvalidCell { cell } => write!(f, "{} is not a cell", cell),
+            SudokuError::Move(e) => write!(f, "{}", e),
+            SudokuError::Cancelled => write!(f, "the search was cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for SudokuError {}
+
+impl From<MoveError> for SudokuError {
+    fn from(e: MoveError) -> Self {
+        SudokuError::Move(e)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    // Not the prelude, it has another `Strategy`
+    use proptest::prelude::{any, prop, prop_assert, prop_assert_eq, proptest, Just};
+    use proptest::strategy::Strategy as _;
+
+    const EASY: &str =
+        "..3.2.6..9..3.5..1..18.64....81.29..7.......8..67.82....26.95..8..2.3..9..5.1.3..";
+    const ESCARGOT: &str =
+        "1....7.9..3..2...8..96..5....53..9...1..8...26....4...3......1..4......7..7...3..";
+    const HARD: &str =
+        "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......";
+    // How many guesses the search takes on them today, a change here
+    // means the search itself changed
+    const GUESSES: [(&str, usize); 3] = [(EASY, 0), (ESCARGOT, 15), (HARD, 25)];
+    // Generated for these tests, it needs an X-Wing
+    const XWING: &str =
+        "6...27.5.....853.4...3...1.83..7.1......4......12.......2.......8......7.7..6.8.9";
+
+    #[test]
+    fn test_difficulty() {
+        let easy = Sudoku::try_from(EASY).unwrap();
+        let hard = Sudoku::try_from(HARD).unwrap();
+        assert_eq!(easy.difficulty(), Some(Difficulty::Easy));
+        assert!(hard.difficulty() > easy.difficulty());
+
+        let mut solved = easy.clone();
+        assert_eq!(solved.solve_stats(), Some(SolveStats { guesses: 0 }));
+        assert_ne!(solved, easy);
+    }
+
+    #[test]
+    fn test_known_hard_puzzles() {
+        // The solutions are the published ones, and the guesses those
+        // in `GUESSES`: a change to the heuristic or the propagation may
+        // move them, but it should not make them grow much
+        let solutions = [
+            (
+                ESCARGOT,
+                "162857493534129678789643521475312986913586742628794135356478219241935867897261354",
+            ),
+            (
+                HARD,
+                "417369825632158947958724316825437169791586432346912758289643571573291684164875293",
+            ),
+        ];
+        for &(puzzle, solution) in solutions.iter() {
+            let (_, guesses) = *GUESSES.iter().find(|&&(p, _)| p == puzzle).unwrap();
+            let mut sudoku = Sudoku::try_from(puzzle).unwrap();
+            assert!(sudoku.is_unique());
+            assert_eq!(sudoku.solve_stats(), Some(SolveStats { guesses }));
+            assert_eq!(sudoku.to_line(), solution);
+        }
+        assert_eq!(Sudoku::try_from(HARD).unwrap().clue_count(), 17);
+    }
+
+    #[test]
+    fn test_score() {
+        // Only singles, one per empty cell
+        let easy = Sudoku::try_from(EASY).unwrap();
+        assert_eq!(easy.score(), 81 - 32);
+
+        // Hard for the search, but the human techniques don't need to
+        // guess at all
+        let hard = Sudoku::try_from(HARD).unwrap();
+        assert!(hard.score() > easy.score());
+        assert!(hard.score() < Technique::Guess.weight());
+
+        let escargot = Sudoku::try_from(ESCARGOT).unwrap();
+        assert!(escargot.score() > hard.score() + Technique::Guess.weight());
+
+        let unsolvable = Sudoku::try_from(HARD.replacen('.', "4", 1).as_str()).unwrap();
+        assert_eq!(unsolvable.score(), u32::MAX);
+    }
+
+    #[test]
+    fn test_hardest_technique() {
+        let easy = Sudoku::try_from(EASY).unwrap();
+        assert_eq!(easy.hardest_technique(), Technique::Singles);
+        let x_wing = Sudoku::try_from(XWING).unwrap();
+        assert_eq!(x_wing.hardest_technique(), Technique::XWing);
+        let escargot = Sudoku::try_from(ESCARGOT).unwrap();
+        assert_eq!(escargot.hardest_technique(), Technique::Guess);
+        let unsolvable = Sudoku::try_from(HARD.replacen('.', "4", 1).as_str()).unwrap();
+        assert_eq!(unsolvable.hardest_technique(), Technique::Guess);
+    }
+
+    #[test]
+    fn test_solve_with_progress() {
+        let mut calls = Vec::new();
+        let mut sudoku = Sudoku::try_from(EASY).unwrap();
+        assert!(sudoku.solve_with_progress(|p| calls.push(p)));
+        assert_eq!(
+            calls,
+            vec![SolveProgress {
+                cells_solved: 81,
+                guesses: 0
+            }]
+        );
+
+        let mut calls = Vec::new();
+        let mut sudoku = Sudoku::try_from(HARD).unwrap();
+        let mut solution = sudoku.clone();
+        let stats = solution.solve_stats().unwrap();
+        assert!(sudoku.solve_with_progress(|p| calls.push(p)));
+        assert_eq!(sudoku, solution);
+        assert_eq!(calls.len(), stats.guesses / PROGRESS_EVERY + 1);
+        assert!(calls.windows(2).all(|w| w[0].guesses < w[1].guesses));
+        assert!(calls
+            .iter()
+            .all(|p| p.cells_solved > 17 && p.cells_solved <= 81));
+        assert_eq!(calls.last().unwrap().guesses, stats.guesses);
+
+        let mut unsolvable = Sudoku::try_from(HARD.replacen('.', "4", 1).as_str()).unwrap();
+        assert!(!unsolvable.solve_with_progress(|_| panic!("nothing to report")));
+    }
+
+    #[test]
+    fn test_empty() {
+        let mut sudoku = Sudoku::empty();
+        assert_eq!(sudoku.to_line(), ".".repeat(81));
+        assert!(!sudoku.is_unique());
+        assert!(sudoku.solve());
+        assert!(sudoku.is_solved());
+    }
+
+    #[test]
+    fn test_solve_conflicting() {
+        let mut solution = Sudoku::try_from(HARD).unwrap();
+        solution.solve();
+
+        // Swapping two cells of a row breaks their columns
+        let mut conflict = solution.clone();
+        conflict.0.swap(0, 1);
+        let mut sudoku = conflict.clone();
+        assert!(!sudoku.solve());
+        assert_eq!(sudoku, conflict);
+        assert!(!sudoku.solve_iterative());
+
+        // The propagation already rejects that grid, check values that
+        // are already decided, which the search hands back as they are
+        let wrong = Values(conflict.0.map(|d| Possible(1 << (d - 1))));
+        assert_eq!(Sudoku::solve_from(wrong), None);
+    }
+
+    #[test]
+    fn test_most_constrained() {
+        let mut values = Values::new();
+        values.0[3] = Possible(0b1110);
+        values.0[7] = Possible(0b0110);
+        values.0[40] = Possible(0b1100);
+        values.0[80] = Possible(0b1010);
+        // Decided cells don't count
+        values.0[1] = Possible(0b0010);
+        assert_eq!(values.most_constrained(), 7);
+
+        values.0[7] = Possible(0b0100);
+        assert_eq!(values.most_constrained(), 40);
+    }
+
+    #[test]
+    fn test_solve_max_depth() {
+        // There can't be more nested guesses than cells
+        let mut sudoku = Sudoku::try_from(HARD).unwrap();
+        assert_eq!(sudoku.solve_max_depth(81), Some(true));
+
+        let mut sudoku = Sudoku::try_from(HARD).unwrap();
+        assert_eq!(sudoku.solve_max_depth(1), None);
+        assert_eq!(sudoku, Sudoku::try_from(HARD).unwrap());
+
+        // No guessing needed at all
+        let mut sudoku = Sudoku::try_from(EASY).unwrap();
+        assert_eq!(sudoku.solve_max_depth(0), Some(true));
+
+        // Two 4s in the first row
+        let mut sudoku = Sudoku::try_from(HARD.replacen('.', "4", 1).as_str()).unwrap();
+        assert_eq!(sudoku.solve_max_depth(81), Some(false));
+    }
+
+    #[test]
+    fn test_backtrack_count() {
+        for &(puzzle, guesses) in GUESSES.iter() {
+            assert_eq!(Sudoku::try_from(puzzle).unwrap().backtrack_count(), guesses);
+        }
+        let escargot = Sudoku::try_from(ESCARGOT).unwrap();
+        assert_eq!(
+            escargot.backtrack_count(),
+            escargot.clone().solve_stats().unwrap().guesses
+        );
+    }
+
+    #[test]
+    fn test_solve_with_heuristic() {
+        let puzzle = Sudoku::try_from(ESCARGOT).unwrap();
+        let mut expected = puzzle.clone();
+        let stats = expected.solve_stats();
+
+        let mut sudoku = puzzle.clone();
+        assert_eq!(
+            sudoku.solve_with_heuristic(Heuristic::MostConstrained),
+            stats
+        );
+        assert_eq!(sudoku, expected);
+
+        let mut sudoku = puzzle.clone();
+        let naive = sudoku.solve_with_heuristic(Heuristic::FirstEmpty).unwrap();
+        assert_eq!(sudoku, expected);
+        assert_ne!(Some(naive), stats);
+
+        let mut unsolvable = Sudoku::try_from(HARD.replacen('.', "4", 1).as_str()).unwrap();
+        assert_eq!(unsolvable.solve_with_heuristic(Heuristic::FirstEmpty), None);
+    }
+
+    #[test]
+    fn test_solve_from() {
+        let puzzle = Sudoku::try_from(ESCARGOT).unwrap();
+        let mut solution = puzzle.clone();
+        solution.solve();
+        let values = puzzle.values().unwrap();
+        assert_eq!(Sudoku::solve_from(values), Some(solution.clone()));
+
+        // The only solution is gone
+        let cell = (0..81).find(|&c| puzzle.0[c] == 0).unwrap();
+        let mut wrong = values;
+        assert!(wrong.remove(solution.0[cell], cell));
+        assert_eq!(Sudoku::solve_from(wrong), None);
+
+        let unsolvable = Sudoku::try_from(HARD.replacen('.', "4", 1).as_str()).unwrap();
+        assert!(unsolvable.values().is_none());
+    }
+
+    #[cfg(feature = "log")]
+    #[test]
+    fn test_log() {
+        use std::sync::Mutex;
+
+        struct Logger(Mutex<Vec<String>>);
+
+        impl log::Log for Logger {
+            fn enabled(&self, metadata: &log::Metadata) -> bool {
+                metadata.target() == "sudoku::search"
+            }
+
+            fn log(&self, record: &log::Record) {
+                if self.enabled(record.metadata()) {
+                    self.0.lock().unwrap().push(record.args().to_string());
+                }
+            }
+
+            fn flush(&self) {}
+        }
+
+        static LOGGER: Logger = Logger(Mutex::new(Vec::new()));
+        log::set_logger(&LOGGER).unwrap();
+        log::set_max_level(log::LevelFilter::Trace);
+
+        let mut sudoku = Sudoku::try_from(ESCARGOT).unwrap();
+        sudoku.solve();
+        // Other tests may be logging at the same time
+        let records = LOGGER.0.lock().unwrap();
+        assert!(records.contains(&format!("solving {}", ESCARGOT)));
+        assert!(records.iter().any(|r| r.starts_with("guessing ")));
+        assert!(records
+            .iter()
+            .any(|r| r.starts_with("contradiction after ")));
+        assert!(records.contains(&"solved: true after 15 guesses".to_string()));
+    }
+
+    #[test]
+    fn test_solve_cancellable() {
+        let cancel = AtomicBool::new(false);
+        let mut sudoku = Sudoku::try_from(ESCARGOT).unwrap();
+        assert_eq!(sudoku.solve_cancellable(&cancel), Ok(true));
+        assert!(sudoku.is_solved());
+
+        cancel.store(true, Ordering::Relaxed);
+        let puzzle = Sudoku::try_from(ESCARGOT).unwrap();
+        let mut sudoku = puzzle.clone();
+        assert_eq!(
+            sudoku.solve_cancellable(&cancel),
+            Err(SudokuError::Cancelled)
+        );
+        assert_eq!(sudoku, puzzle);
+
+        // No guesses, nothing to cancel
+        let mut sudoku = Sudoku::try_from(EASY).unwrap();
+        assert_eq!(sudoku.solve_cancellable(&cancel), Ok(true));
+    }
+
+    #[test]
+    fn test_search_contradiction() {
+        let mut values = Values::new();
+        values.0[10] = Possible(0);
+        assert!(values.search(&mut Search::new(), 0).is_none());
+
+        // Even if every other cell is already decided
+        let mut values = Values::from_grid(&Sudoku::try_from(EASY).unwrap().0)
+            .unwrap()
+            .search(&mut Search::new(), 0)
+            .unwrap();
+        values.0[80] = Possible(0);
+        assert!(values.search(&mut Search::new(), 0).is_none());
+    }
+
+    #[test]
+    fn test_solve_logical() {
+        let mut logical = Sudoku::try_from(EASY).unwrap();
+        let mut solution = logical.clone();
+        solution.solve();
+        assert_eq!(logical.solve_logical(), Ok(true));
+        assert_eq!(logical, solution);
+
+        // The search needs to guess, the techniques don't
+        let mut logical = Sudoku::try_from(HARD).unwrap();
+        assert_eq!(logical.solve_logical(), Ok(true));
+        assert!(logical.0.iter().all(|&d| d != 0));
+
+        let puzzle = Sudoku::try_from(ESCARGOT).unwrap();
+        let mut logical = puzzle.clone();
+        let mut solution = puzzle.clone();
+        solution.solve();
+        assert_eq!(logical.solve_logical(), Ok(false));
+        // Whatever was decided is right
+        assert!(logical.diff(&solution).iter().all(|&(_, d, _)| d == 0));
+        assert!(!puzzle.diff(&logical).is_empty());
+
+        let unsolvable = Sudoku::try_from(HARD.replacen('.', "4", 1).as_str()).unwrap();
+        let mut logical = unsolvable.clone();
+        assert_eq!(logical.solve_logical(), Err(SudokuError::Contradiction));
+        assert_eq!(logical, unsolvable);
+    }
+
+    /// Knows the solution, and removes one wrong candidate at a time
+    struct Oracle(Sudoku);
+
+    impl Strategy for Oracle {
+        fn apply(&self, values: &mut Values) -> bool {
+            (0..81).any(|cell| {
+                let right = (self.0).0[cell];
+                let wrong = values.candidates(cell).values().find(|&d| d != right);
+                wrong.is_some_and(|d| values.remove(d, cell))
+            })
+        }
+
+        fn name(&self) -> &str {
+            "oracle"
+        }
+    }
+
+    #[test]
+    fn test_solve_logical_with() {
+        let puzzle = Sudoku::try_from(ESCARGOT).unwrap();
+        let mut solution = puzzle.clone();
+        solution.solve();
+
+        let mut strategies = Technique::pipeline();
+        assert_eq!(puzzle.clone().solve_logical_with(&strategies), Ok(false));
+
+        strategies.push(Box::new(Oracle(solution.clone())));
+        assert_eq!(strategies.last().unwrap().name(), "oracle");
+        let mut logical = puzzle.clone();
+        assert_eq!(logical.solve_logical_with(&strategies), Ok(true));
+        assert_eq!(logical, solution);
+
+        // No strategies, only propagation
+        let mut logical = Sudoku::try_from(EASY).unwrap();
+        assert_eq!(logical.solve_logical_with(&[]), Ok(true));
+        let mut logical = puzzle;
+        assert_eq!(logical.solve_logical_with(&[]), Ok(false));
+    }
+
+    #[test]
+    fn test_solve_with_strategies() {
+        use Technique::*;
+        let sudoku = Sudoku::try_from(XWING).unwrap();
+        assert_eq!(sudoku.solve_with_strategies(&[LockedCandidates]), None);
+        let mut solution = sudoku.clone();
+        assert!(solution.solve());
+        assert_eq!(
+            sudoku.solve_with_strategies(&[LockedCandidates, XWing]),
+            Some(solution.clone())
+        );
+        assert_eq!(
+            sudoku.solve_with_strategies(&[LockedCandidates, Guess]),
+            Some(solution)
+        );
+
+        let unsolvable = Sudoku::try_from(HARD.replacen('.', "4", 1).as_str()).unwrap();
+        assert_eq!(unsolvable.solve_with_strategies(&[Guess]), None);
+        // Nor the puzzles that need a guess
+        let escargot = Sudoku::try_from(ESCARGOT).unwrap();
+        assert_eq!(escargot.solve_with_strategies(&Technique::PIPELINE), None);
+    }
+
+    #[test]
+    fn test_search_iterative() {
+        for puzzle in &[EASY, HARD] {
+            let values = Values::from_grid(&Sudoku::try_from(*puzzle).unwrap().0).unwrap();
+
+            let mut recursive = Search::new();
+            let mut iterative = Search::new();
+            let expected = values.search(&mut recursive, 0).unwrap();
+            let found = values
+                .search_iterative(&mut iterative, &mut Vec::new())
+                .unwrap();
+            assert_eq!(found.0, expected.0);
+            assert_eq!(iterative.stats, recursive.stats);
+        }
+
+        let mut sudoku = Sudoku::try_from(HARD.replacen('.', "4", 1).as_str()).unwrap();
+        assert!(!sudoku.solve_iterative());
+    }
+
+    #[test]
+    fn test_display_with() {
+        let sudoku = Sudoku::try_from(HARD).unwrap();
+        assert_eq!(
+            sudoku.display_with(DisplayOptions::default()),
+            sudoku.to_string()
+        );
+
+        let compact = sudoku.display_with(DisplayOptions::compact());
+        let lines: Vec<&str> = compact.lines().collect();
+        assert_eq!(lines.len(), 13);
+        assert_eq!(lines[0], "+---+---+---+");
+        assert_eq!(lines[1], "|4..|...|8.5|");
+
+        let plain = sudoku.display_with(DisplayOptions::plain());
+        assert_eq!(plain.lines().next(), Some("4 . . . . . 8 . 5"));
+        assert_eq!(plain.lines().count(), 9);
+
+        let bare = DisplayOptions {
+            compact: true,
+            borders: false,
+            ..DisplayOptions::default()
+        };
+        let bare = sudoku.display_with(bare);
+        assert_eq!(bare.replace('\n', ""), HARD);
+        assert_eq!(Sudoku::try_from(bare.as_str()), Ok(sudoku));
+    }
+
+    #[test]
+    fn test_display_cell_width() {
+        let sudoku = Sudoku::try_from(HARD).unwrap();
+        let with_width = |width| DisplayOptions {
+            cell_width: Some(width),
+            ..DisplayOptions::default()
+        };
+        assert_eq!(
+            sudoku.display_with(with_width(1)),
+            sudoku.display_with(DisplayOptions::compact())
+        );
+        assert_eq!(sudoku.display_with(with_width(2)), sudoku.to_string());
+        let airy = sudoku.display_with(with_width(3));
+        assert_eq!(airy.lines().nth(1), Some("| 4  .  . | .  .  . | 8  .  5 |"));
+
+        for width in 1..=3 {
+            let grid = sudoku.display_with(with_width(width));
+            let lines: Vec<&str> = grid.lines().collect();
+            assert_eq!(lines.len(), 13);
+            // Every line is as long as the borders, with the box
+            // separators at the same columns
+            let border = lines[0];
+            assert_eq!(border.len(), 9 * width + 4);
+            for line in &lines {
+                assert_eq!(line.len(), border.len());
+                for (at, c) in border.char_indices().filter(|&(_, c)| c == '+') {
+                    let expected = if line.starts_with('+') { c } else { '|' };
+                    assert_eq!(line[at..].chars().next(), Some(expected));
+                }
+            }
+            assert_eq!(Sudoku::try_from(grid.as_str()), Ok(sudoku.clone()));
+        }
+
+        let plain = DisplayOptions {
+            cell_width: Some(3),
+            ..DisplayOptions::plain()
+        };
+        let plain = sudoku.display_with(plain);
+        assert_eq!(plain.lines().next(), Some("4  .  .  .  .  .  8  .  5"));
+        // Never narrower than a digit
+        assert_eq!(
+            sudoku.display_with(with_width(0)),
+            sudoku.display_with(with_width(1))
+        );
+    }
+
+    #[test]
+    fn test_solved_grid() {
+        let puzzle = Sudoku::try_from(HARD).unwrap();
+        let values = puzzle.values().unwrap();
+        assert_eq!(values.solved_grid(), None);
+
+        let mut solution = puzzle.clone();
+        solution.solve();
+        let solved = values.search(&mut Search::new(), 0).unwrap();
+        assert_eq!(solved.solved_grid(), Some(solution.0));
+        assert_eq!(solution.values().unwrap().solved_grid(), Some(solution.0));
+
+        // A cell left without possible values is not solved either
+        let mut wrong = solved;
+        wrong.0[0] = Possible(0);
+        assert_eq!(wrong.solved_grid(), None);
+    }
+
+    #[test]
+    fn test_values_out_of_range() {
+        let mut values = Sudoku::try_from(HARD).unwrap().values().unwrap();
+        let before = values;
+        assert!(!values.remove(0, 1));
+        assert!(!values.remove(10, 1));
+        assert!(!values.remove(255, 80));
+        assert_eq!(values.0[..], before.0[..]);
+        assert!(values.remove(9, 80));
+    }
+
+    #[test]
+    #[should_panic(expected = "81 is not a cell")]
+    fn test_values_cell_out_of_range() {
+        let mut values = Sudoku::empty().values().unwrap();
+        values.remove(1, 81);
+    }
+
+    #[test]
+    fn test_reveal() {
+        let puzzle = Sudoku::try_from(HARD).unwrap();
+        let mut solution = puzzle.clone();
+        solution.solve();
+
+        let mut sudoku = puzzle.clone();
+        assert_eq!(sudoku.reveal(1), Some(1));
+        assert_eq!(sudoku.diff(&puzzle), vec![(1, 1, 0)]);
+        assert_eq!(sudoku.reveal(1), None);
+        assert_eq!(sudoku.reveal(0), None);
+        assert_eq!(sudoku.reveal(81), None);
+        for cell in 0..81 {
+            if puzzle.0[cell] == 0 && cell != 1 {
+                assert_eq!(sudoku.reveal(cell), Some(solution.0[cell]));
+            }
+        }
+        assert_eq!(sudoku, solution);
+
+        let mut unsolvable = Sudoku::try_from(HARD.replacen('.', "4", 1).as_str()).unwrap();
+        let before = unsolvable.clone();
+        assert_eq!(unsolvable.reveal(2), None);
+        assert_eq!(unsolvable, before);
+    }
+
+    #[test]
+    fn test_with_additional_given() {
+        let puzzle = Sudoku::try_from(HARD).unwrap();
+        let mut solution = puzzle.clone();
+        solution.solve();
+
+        let more = puzzle.with_additional_given(1, 1).unwrap();
+        assert_eq!(more.diff(&puzzle), vec![(1, 1, 0)]);
+        assert_eq!(more.clue_count(), 18);
+        assert!(more.is_given(1));
+        // Chained, the clues pile up and the solution stays
+        let mut chained = more.with_additional_given(80, solution.0[80]).unwrap();
+        assert_eq!(chained.clue_count(), 19);
+        assert!(chained.solve());
+        assert_eq!(chained, solution);
+        assert_eq!(puzzle.with_additional_given(0, 4), Ok(puzzle.clone()));
+
+        // A digit played becomes a clue, and can't be undone
+        let mut played = puzzle.clone();
+        played.play(1, 7).unwrap();
+        let clue = played.with_additional_given(1, 1).unwrap();
+        assert!(clue.is_given(1));
+        assert!(!clue.clone().undo());
+
+        // There is a 4 in the row, and a 3 in the square
+        let refused = |cell, digit| puzzle.with_additional_given(cell, digit).unwrap_err();
+        assert_eq!(refused(1, 4), SudokuError::Move(MoveError::Conflict(0)));
+        assert_eq!(refused(1, 3), SudokuError::Move(MoveError::Conflict(10)));
+        assert_eq!(refused(0, 1), SudokuError::Move(MoveError::CellIsGiven));
+        assert_eq!(
+            refused(81, 1),
+            SudokuError::Move(MoveError::InvalidCell(81))
+        );
+        assert_eq!(refused(1, 0), SudokuError::Move(MoveError::InvalidDigit(0)));
+        assert_eq!(
+            refused(1, 10),
+            SudokuError::Move(MoveError::InvalidDigit(10))
+        );
+        assert_eq!(refused(0, 1).to_string(), "clues can't be changed");
+    }
+
+    #[test]
+    fn test_placements() {
+        let sudoku = Sudoku::try_from(HARD).unwrap();
+        let values = sudoku.values().unwrap();
+        for digit in 1..=9 {
+            let placements = sudoku.placements(digit);
+            assert!(!placements.is_empty());
+            let expected: Vec<usize> = (0..81)
+                .filter(|&c| sudoku.0[c] == 0 && values.candidates(c).contains(digit))
+                .collect();
+            assert_eq!(placements, expected);
+        }
+        // There is a 4 in the first row already
+        assert!(sudoku.placements(4).iter().all(|&c| c >= 9));
+
+        let mut solved = sudoku.clone();
+        solved.solve();
+        assert!((1..=9).all(|d| solved.placements(d).is_empty()));
+
+        let unsolvable = Sudoku::try_from(HARD.replacen('.', "4", 1).as_str()).unwrap();
+        assert!(unsolvable.placements(1).is_empty());
+    }
+
+    #[test]
+    fn test_why_unsolvable() {
+        let mut sudoku = Sudoku::try_from(HARD).unwrap();
+        assert_eq!(sudoku.why_unsolvable(), None);
+        assert_eq!(Sudoku::empty().why_unsolvable(), None);
+
+        // A second 4 in the first row
+        sudoku.0[1] = 4;
+        assert_eq!(
+            sudoku.why_unsolvable(),
+            Some(Explanation::NoCandidates { cell: 1 })
+        );
+
+        // Propagating a 9 there takes every 6 out of the bottom left
+        // square
+        let mut sudoku = Sudoku::try_from(HARD).unwrap();
+        sudoku.0[58] = 9;
+        let explanation = sudoku.why_unsolvable().unwrap();
+        assert_eq!(
+            explanation,
+            Explanation::NoPlace {
+                digit: 6,
+                unit: Unit::Square(6)
+            }
+        );
+        assert_eq!(explanation.to_string(), "no place for a 6 in square 6");
+
+        // Only guessing finds this one out
+        let mut sudoku = Sudoku::try_from(HARD).unwrap();
+        sudoku.0[1] = 9;
+        assert_eq!(sudoku.why_unsolvable(), Some(Explanation::NoSolution));
+        assert!(!sudoku.solve());
+    }
+
+    #[test]
+    fn test_normalize() {
+        let grid = "\
+            4 . . |. . . |8 . 5
+            . 3 . |. . . |. . .
+            . . . |7 . . |. . .
+            ------+------+------
+            . 2 . |. . . |. 6 .
+            . . . |. 8 . |4 . .
+            . . . |. 1 . |. . .
+            ------+------+------
+            . . . |6 . 3 |. 7 .
+            5 . . |2 . . |. . .
+            1 . 4 |. . . |. . .";
+        assert_eq!(Sudoku::normalize(grid).unwrap(), HARD.replace('.', "0"));
+        assert_eq!(Sudoku::normalize(HARD), Sudoku::normalize(grid));
+        assert_eq!(Sudoku::normalize(&HARD[1..]), Err(SudokuError::TooFewCells));
+        assert_eq!(
+            Sudoku::normalize(&HARD.replacen('.', "·", 3)),
+            Sudoku::normalize(HARD)
+        );
+    }
+
+    #[test]
+    fn test_middle_dot() {
+        let sudoku = Sudoku::try_from(HARD).unwrap();
+        let dotted = DisplayOptions {
+            blank: '·',
+            ..DisplayOptions::default()
+        };
+        let shown = sudoku.display_with(dotted);
+        assert_eq!(shown.lines().nth(1), Some("|4 · · |· · · |8 · 5 |"));
+        assert_eq!(shown.replace('·', "."), sudoku.to_string());
+        assert!(shown.lines().all(|line| line.chars().count() == 3 * 7 + 1));
+
+        let line = HARD.replace('.', "·");
+        assert_eq!(Sudoku::try_from(line.as_str()), Ok(sudoku.clone()));
+        assert_eq!(Sudoku::parse_strict(&line), Ok(sudoku.clone()));
+        assert_eq!(Sudoku::try_from(shown.as_str()), Ok(sudoku.clone()));
+        assert_eq!(
+            Sudoku::parse_strict(&format!("{}·", line)),
+            Err(SudokuError::TooManyCells)
+        );
+        assert_eq!(
+            Sudoku::try_from(&line[..line.len() - 2]),
+            Err(SudokuError::TooFewCells)
+        );
+        let csv = sudoku
+            .display_with(DisplayOptions::plain())
+            .replace('.', "·");
+        assert_eq!(Sudoku::parse_csv(&csv), Ok(sudoku));
+    }
+
+    #[test]
+    fn test_display_line_lengths() {
+        let sudoku = Sudoku::try_from(HARD).unwrap();
+        for options in [DisplayOptions::default(), DisplayOptions::compact()].iter() {
+            let shown = sudoku.display_with(*options);
+            let lengths: Vec<usize> = shown.lines().map(str::len).collect();
+            assert_eq!(lengths.len(), 13);
+            assert!(lengths.iter().all(|&len| len == lengths[0]));
+        }
+        let shown = sudoku.to_string();
+        assert!(shown.lines().all(|line| line.len() == 3 * 7 + 1));
+    }
+
+    #[test]
+    fn test_naked_singles() {
+        // Propagation alone solves it
+        let easy = Sudoku::try_from(EASY).unwrap();
+        let mut solution = easy.clone();
+        solution.solve();
+        let filled: Vec<(usize, u8)> = easy
+            .diff(&solution)
+            .iter()
+            .map(|&(i, _, d)| (i, d))
+            .collect();
+        assert_eq!(easy.naked_singles(), filled);
+
+        let hard = Sudoku::try_from(HARD).unwrap();
+        let mut solution = hard.clone();
+        solution.solve();
+        let singles = hard.naked_singles();
+        assert!(!singles.is_empty());
+        assert!(singles.len() < 81 - 17);
+        assert!(singles
+            .iter()
+            .all(|&(i, d)| hard.0[i] == 0 && solution.0[i] == d));
+
+        assert_eq!(solution.naked_singles(), vec![]);
+        let unsolvable = Sudoku::try_from(HARD.replacen('.', "4", 1).as_str()).unwrap();
+        assert_eq!(unsolvable.naked_singles(), vec![]);
+    }
+
+    #[test]
+    fn test_candidates_display() {
+        let display = Sudoku::try_from(HARD).unwrap().candidates_display();
+        let lines: Vec<&str> = display.lines().collect();
+
+        // 9 rows of 3 lines plus a separator above and below each row
+        assert_eq!(lines.len(), 9 * 4 + 1);
+        assert!(lines.iter().all(|l| l.len() == lines[0].len()));
+        assert_eq!(lines[0], "+-------------+-------------+-------------+");
+
+        // The 4 at the top left corner is a given, the cell at its
+        // right sees 2, 3, 4, 5 and 8
+        assert_eq!(&lines[1][..13], "|     1.. 12.");
+        assert_eq!(&lines[2][..13], "|  4  ..6 ..6");
+        assert_eq!(&lines[3][..13], "|     7.9 7.9");
+
+        // Easy puzzles are solved by propagation alone
+        let display = Sudoku::try_from(EASY).unwrap().candidates_display();
+        assert_eq!(display.matches(char::is_numeric).count(), 81);
+        assert!(!display.contains('.'));
+    }
+
+    #[test]
+    fn test_candidate_counts() {
+        let mut sudoku = Sudoku::try_from(HARD).unwrap();
+        let counts = sudoku.candidate_counts();
+        assert_eq!(counts[0], 1);
+        assert_eq!(counts[1], 4);
+        assert!(counts.iter().all(|&c| (1..=9).contains(&c)));
+
+        sudoku.solve();
+        assert!(sudoku.candidate_counts().iter().all(|&c| c == 1));
+
+        let sudoku = Sudoku::try_from(HARD.replacen('.', "4", 1).as_str()).unwrap();
+        assert!(sudoku.candidate_counts().iter().all(|&c| c == 0));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!(Sudoku::try_from(HARD).is_ok());
+        assert_eq!(Sudoku::try_from(&HARD[1..]), Err(SudokuError::TooFewCells));
+
+        // Separators are fine, extra cells are only an error when strict
+        let with_extra = format!("{}|\n5", HARD);
+        assert_eq!(
+            Sudoku::try_from(with_extra.as_str()),
+            Sudoku::try_from(HARD)
+        );
+        assert_eq!(
+            Sudoku::parse_strict(&with_extra),
+            Err(SudokuError::TooManyCells)
+        );
+        assert_eq!(
+            Sudoku::parse_strict(&format!("{}.", HARD)),
+            Err(SudokuError::TooManyCells)
+        );
+        assert_eq!(
+            Sudoku::parse_strict(&format!("{} |\n+---+\n", HARD)),
+            Sudoku::try_from(HARD)
+        );
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let sudoku = Sudoku::try_from(HARD).unwrap();
+        let csv: Vec<String> = sudoku
+            .to_rows()
+            .iter()
+            .map(|row| {
+                let row: Vec<String> = row.iter().map(u8::to_string).collect();
+                row.join(",")
+            })
+            .collect();
+        assert!(csv[0].starts_with("4,0,0,0,0,0,8,0,5"));
+        assert_eq!(Sudoku::parse_csv(&csv.join("\n")), Ok(sudoku.clone()));
+        let spaces = csv.join(" ").replace(',', " ").replacen('0', ".", 1);
+        assert_eq!(Sudoku::parse_csv(&spaces), Ok(sudoku));
+
+        let csv = csv.join(",");
+        assert_eq!(Sudoku::parse_csv(&csv[2..]), Err(SudokuError::TooFewCells));
+        assert_eq!(
+            Sudoku::parse_csv(&format!("{},0", csv)),
+            Err(SudokuError::TooManyCells)
+        );
+        assert_eq!(
+            Sudoku::parse_csv(&csv.replacen("0", "x", 1)),
+            Err(SudokuError::InvalidToken { cell: 1 })
+        );
+        assert_eq!(
+            Sudoku::parse_csv(&csv.replacen("0", "10", 1)),
+            Err(SudokuError::InvalidDigit { cell: 1, digit: 10 })
+        );
+    }
+
+    #[test]
+    fn test_from_grid() {
+        let mut grid = [[0; 9]; 9];
+        grid[0][8] = 5;
+        grid[8][0] = 1;
+        let sudoku = Sudoku::from(grid);
+        assert_eq!(sudoku.0[8], 5);
+        assert_eq!(sudoku.0[72], 1);
+        assert_eq!(sudoku.to_grid(), grid);
+
+        let hard = Sudoku::try_from(HARD).unwrap();
+        assert_eq!(Sudoku::from(hard.to_grid()), hard);
+
+        // Not a digit, an empty cell
+        let mut grid = [[0; 9]; 9];
+        grid[1][2] = 10;
+        assert_eq!(Sudoku::from(grid), Sudoku::empty());
+        assert_eq!(
+            Sudoku::from_rows(grid),
+            Err(SudokuError::InvalidDigit {
+                cell: 11,
+                digit: 10
+            })
+        );
+    }
+
+    #[test]
+    fn test_try_from_bytes() {
+        let sudoku = Sudoku::try_from(HARD).unwrap();
+        let cells = sudoku.0.to_vec();
+        assert_eq!(Sudoku::try_from(&cells[..]), Ok(sudoku.clone()));
+        assert_eq!(Sudoku::try_from(cells.clone()), Ok(sudoku));
+
+        assert_eq!(Sudoku::try_from(&cells[1..]), Err(SudokuError::TooFewCells));
+        assert_eq!(
+            Sudoku::try_from(vec![0; 82]),
+            Err(SudokuError::TooManyCells)
+        );
+        let mut cells = cells;
+        cells[3] = 10;
+        assert_eq!(
+            Sudoku::try_from(cells),
+            Err(SudokuError::InvalidDigit { cell: 3, digit: 10 })
+        );
+    }
+
+    #[test]
+    fn test_solution_grid() {
+        let puzzle = Sudoku::try_from(HARD).unwrap();
+        let before = puzzle.clone();
+        let rows = puzzle.solution_grid().unwrap();
+        assert_eq!(puzzle, before);
+
+        let mut solution = puzzle.clone();
+        solution.solve();
+        assert_eq!(rows, solution.to_rows());
+        for (cell, &digit) in solution.0.iter().enumerate() {
+            assert_eq!(rows[cell / 9][cell % 9], digit);
+        }
+
+        let unsolvable = Sudoku::try_from(HARD.replacen('.', "4", 1).as_str()).unwrap();
+        assert_eq!(unsolvable.solution_grid(), None);
+        let ambiguous = Sudoku::try_from(HARD.replacen('4', ".", 1).as_str()).unwrap();
+        assert_eq!(ambiguous.solution_grid(), None);
+        assert_eq!(solution.solution_grid(), Some(rows));
+    }
+
+    #[test]
+    fn test_rows() {
+        let sudoku = Sudoku::try_from(HARD).unwrap();
+        let rows = sudoku.to_rows();
+        assert_eq!(rows[0], [4, 0, 0, 0, 0, 0, 8, 0, 5]);
+        assert_eq!(rows[8], [1, 0, 4, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(Sudoku::from_rows(rows), Ok(sudoku));
+
+        let mut rows = [[0; 9]; 9];
+        rows[1][2] = 10;
+        assert_eq!(
+            Sudoku::from_rows(rows),
+            Err(SudokuError::InvalidDigit {
+                cell: 11,
+                digit: 10
+            })
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_solve_async() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        let mut expected = Sudoku::try_from(HARD).unwrap();
+        expected.solve();
+        let solution = runtime.block_on(Sudoku::try_from(HARD).unwrap().solve_async());
+        assert_eq!(solution, Some(expected));
+
+        let unsolvable = Sudoku::try_from(HARD.replacen('.', "4", 1).as_str()).unwrap();
+        assert_eq!(runtime.block_on(unsolvable.solve_async()), None);
+    }
+
+    #[test]
+    fn test_to_line() {
+        let sudoku = Sudoku::try_from(HARD).unwrap();
+        assert_eq!(sudoku.to_line(), HARD);
+        assert_eq!(Sudoku::try_from(sudoku.to_line().as_str()), Ok(sudoku));
+    }
+
+    #[test]
+    fn test_to_line_zero() {
+        let sudoku = Sudoku::try_from(HARD).unwrap();
+        let line = sudoku.to_line_zero();
+        assert_eq!(line, HARD.replace('.', "0"));
+        assert_eq!(Sudoku::try_from(line.as_str()), Ok(sudoku.clone()));
+        assert_eq!(
+            Sudoku::try_from(sudoku.to_line().as_str()),
+            Ok(sudoku.clone())
+        );
+
+        let mut solution = sudoku;
+        solution.solve();
+        assert_eq!(solution.to_line_zero(), solution.to_line());
+    }
+
+    #[test]
+    fn test_to_line_annotated() {
+        let puzzle = Sudoku::try_from(HARD).unwrap();
+        let givens = puzzle.givens();
+        assert_eq!(givens.iter().filter(|&&g| g).count(), 17);
+        assert_eq!(puzzle.to_line_annotated(&givens), HARD);
+
+        let mut solution = puzzle.clone();
+        solution.solve();
+        let line = solution.to_line_annotated(&givens);
+        assert!(line.starts_with("4agcfi8b5"));
+        assert_eq!(Sudoku::parse_annotated(&line), Ok((solution, givens)));
+        assert_eq!(
+            Sudoku::parse_annotated(&line[1..]),
+            Err(SudokuError::TooFewCells)
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        let puzzle = Sudoku::try_from(HARD).unwrap();
+        assert_eq!(puzzle.diff(&puzzle), vec![]);
+
+        let mut solution = puzzle.clone();
+        solution.solve();
+        let filled = puzzle.diff(&solution);
+        assert_eq!(filled.len(), 81 - 17);
+        assert_eq!(filled[0], (1, 0, 1));
+        assert!(filled.iter().all(|&(i, a, b)| a == 0 && b == solution.0[i]));
+
+        let mut other = puzzle.clone();
+        other.0[0] = 9;
+        assert_eq!(puzzle.diff(&other), vec![(0, 4, 9)]);
+    }
+
+    #[test]
+    fn test_solution_class() {
+        let hard = Sudoku::try_from(HARD).unwrap();
+        assert_eq!(hard.solution_class(), SolutionClass::Unique);
+        let sixteen = Sudoku::try_from(HARD.replacen('4', ".", 1).as_str()).unwrap();
+        assert_eq!(sixteen.solution_class(), SolutionClass::Multiple);
+        assert_eq!(Sudoku::empty().solution_class(), SolutionClass::Multiple);
+        let unsolvable = Sudoku::try_from(HARD.replacen('.', "4", 1).as_str()).unwrap();
+        assert_eq!(unsolvable.solution_class(), SolutionClass::None);
+    }
+
+    #[test]
+    fn test_clue_distribution() {
+        let (rows, columns, squares) = Sudoku::try_from(HARD).unwrap().clue_distribution();
+        assert_eq!(rows, [3, 1, 1, 2, 2, 1, 3, 2, 2]);
+        assert_eq!(columns, [3, 2, 1, 3, 2, 1, 2, 2, 1]);
+        assert_eq!(squares, [2, 1, 2, 1, 2, 2, 3, 3, 1]);
+        assert_eq!(rows.iter().sum::<u8>(), 17);
+    }
+
+    #[test]
+    fn test_digit_counts() {
+        let mut sudoku = Sudoku::try_from(HARD).unwrap();
+        assert_eq!(sudoku.digit_counts(), [2, 2, 2, 3, 2, 2, 2, 2, 0]);
+        sudoku.solve();
+        assert_eq!(sudoku.digit_counts(), [9; 9]);
+        assert_eq!(Sudoku::empty().digit_counts(), [0; 9]);
+    }
+
+    #[test]
+    fn test_solve_all_into() {
+        let mut solution = Sudoku::try_from(HARD).unwrap();
+        solution.solve();
+        // The 1s and 3s of the first two rows can be swapped
+        let mut two = solution.clone();
+        for &cell in &[1, 3, 10, 12] {
+            two.0[cell] = 0;
+        }
+
+        let mut buf = Vec::with_capacity(4);
+        buf.push([0; 81]);
+        assert_eq!(two.solve_all_into(&mut buf, 10), 2);
+        assert_eq!(buf.len(), 3);
+        assert_eq!(buf[0], [0; 81]);
+        assert!(buf.contains(&solution.0));
+        let (first, second) = (
+            Sudoku(buf[1], History::default()),
+            Sudoku(buf[2], History::default()),
+        );
+        assert!(first.is_solved() && second.is_solved());
+        assert_eq!(first.diff(&second).len(), 4);
+
+        assert_eq!(two.solve_all_into(&mut buf, 1), 1);
+        assert_eq!(buf.len(), 4);
+        assert_eq!(two.solve_all_into(&mut buf, 0), 0);
+        let unsolvable = Sudoku::try_from(HARD.replacen('.', "4", 1).as_str()).unwrap();
+        assert_eq!(unsolvable.solve_all_into(&mut buf, 10), 0);
+        assert_eq!(buf.len(), 4);
+
+        // Stops at the limit even with more solutions than could ever
+        // be listed
+        assert_eq!(Sudoku::empty().solve_all_into(&mut buf, 1000), 1000);
+        assert_eq!(buf.len(), 1004);
+        assert!(buf[4..]
+            .iter()
+            .all(|&grid| Sudoku(grid, History::default()).is_solved()));
+    }
+
+    #[test]
+    fn test_minimal_unsat_core() {
+        let hard = Sudoku::try_from(HARD).unwrap();
+        assert_eq!(hard.minimal_unsat_core(), Vec::<usize>::new());
+
+        // Two 4s in the first row
+        let mut unsolvable = hard.clone();
+        unsolvable.0[3] = 4;
+        assert_eq!(unsolvable.minimal_unsat_core(), vec![0, 3]);
+
+        // No two clues clash, but the top right corner can only be a
+        // 9, and there is one below it already.  The 1 in the middle
+        // has nothing to do with it.
+        let mut unsolvable = Sudoku::empty();
+        unsolvable.0[..8].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        unsolvable.0[80] = 9;
+        unsolvable.0[30] = 1;
+        assert_eq!(
+            unsolvable.minimal_unsat_core(),
+            vec![0, 1, 2, 3, 4, 5, 6, 7, 80]
+        );
+    }
+
+    #[test]
+    fn test_is_unique() {
+        assert!(Sudoku::try_from(HARD).unwrap().is_unique());
+        assert!(Sudoku::try_from(EASY).unwrap().is_unique());
+
+        let empty = Sudoku::try_from(".".repeat(81).as_str()).unwrap();
+        assert!(!empty.is_unique());
+
+        let unsolvable = Sudoku::try_from(HARD.replacen('.', "4", 1).as_str()).unwrap();
+        assert!(!unsolvable.is_unique());
+
+        // Without one of its 17 clues
+        let sixteen = Sudoku::try_from(HARD.replacen('4', ".", 1).as_str()).unwrap();
+        assert_eq!(sixteen.clue_count(), 16);
+        assert!(!sixteen.is_unique());
+        assert_eq!(sixteen.solution_count_capped(2), 2);
+    }
+
+    #[test]
+    fn test_solution_count_capped() {
+        let mut solution = Sudoku::try_from(HARD).unwrap();
+        assert_eq!(solution.solution_count_capped(5), 1);
+        solution.solve();
+        assert_eq!(solution.solution_count_capped(5), 1);
+        assert_eq!(solution.solution_count_capped(0), 0);
+
+        // The 1s and 3s of the first two rows can be swapped
+        let mut two = solution.clone();
+        for &cell in &[1, 3, 10, 12] {
+            two.0[cell] = 0;
+        }
+        assert_eq!(two.solution_count_capped(5), 2);
+        assert_eq!(two.solution_count_capped(2), 2);
+        assert_eq!(two.solution_count_capped(1), 1);
+
+        assert_eq!(Sudoku::empty().solution_count_capped(100), 100);
+        let unsolvable = Sudoku::try_from(HARD.replacen('.', "4", 1).as_str()).unwrap();
+        assert_eq!(unsolvable.solution_count_capped(5), 0);
+    }
+
+    #[test]
+    fn test_solve_batch() {
+        let unsolvable = HARD.replacen('.', "4", 1);
+        let puzzles: Vec<Sudoku> = [EASY, HARD, &unsolvable]
+            .iter()
+            .cycle()
+            .take(30)
+            .map(|p| Sudoku::try_from(*p).unwrap())
+            .collect();
+
+        let sequential: Vec<Option<Sudoku>> =
+            puzzles.iter().cloned().map(Sudoku::into_solution).collect();
+        assert_eq!(solve_batch(puzzles), sequential);
+        assert!(sequential[2].is_none());
+    }
+
+    #[test]
+    fn test_resolve_after_change() {
+        let mut expected = Sudoku::try_from(HARD).unwrap();
+        expected.solve();
+        let mut sudoku = Sudoku::try_from(HARD).unwrap();
+        assert_eq!(sudoku.resolve_after_change(1, 1), Some(expected.clone()));
+        let mut changed = Sudoku::try_from(HARD).unwrap();
+        changed.0[1] = 1;
+        assert_eq!(sudoku, changed);
+
+        // Two 1s in the first row
+        assert_eq!(sudoku.resolve_after_change(2, 1), None);
+        assert_eq!(sudoku, changed);
+
+        let mut sudoku = Sudoku::try_from(HARD).unwrap();
+        assert_eq!(sudoku.resolve_after_change(1, 4), None);
+        assert_eq!(sudoku.resolve_after_change(81, 1), None);
+        assert_eq!(sudoku.resolve_after_change(1, 10), None);
+        assert_eq!(sudoku, Sudoku::try_from(HARD).unwrap());
+
+        // Emptying a clue makes HARD ambiguous but still solvable
+        let solution = sudoku.resolve_after_change(0, 0).unwrap();
+        assert!(solution.0.iter().all(|&d| d != 0));
+        assert_eq!(sudoku.0[0], 0);
+    }
+
+    #[test]
+    fn test_unit_cells() {
+        assert_eq!(Sudoku::row_cells(0), [0, 1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(Sudoku::row_cells(3), [27, 28, 29, 30, 31, 32, 33, 34, 35]);
+        assert_eq!(Sudoku::column_cells(2), [2, 11, 20, 29, 38, 47, 56, 65, 74]);
+        assert_eq!(Sudoku::box_cells(5), [33, 34, 35, 42, 43, 44, 51, 52, 53]);
+        assert_eq!(Sudoku::box_cells(8), [60, 61, 62, 69, 70, 71, 78, 79, 80]);
+
+        // Every cell is once in a row, a column and a box
+        for unit in [Sudoku::row_cells, Sudoku::column_cells, Sudoku::box_cells].iter() {
+            let mut cells: Vec<u8> = (0..9).flat_map(unit).collect();
+            cells.sort_unstable();
+            assert_eq!(cells, (0..81).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn test_peers() {
+        for cell in 0..81 {
+            let mut peers: Vec<u8> = Sudoku::peers(cell).collect();
+            peers.sort_unstable();
+            assert_eq!(peers.len(), 20);
+            assert!(peers.windows(2).all(|w| w[0] < w[1]));
+            assert!(!peers.contains(&cell));
+            let mut in_units: Vec<u8> = Sudoku::units(cell).iter().flatten().copied().collect();
+            in_units.sort_unstable();
+            in_units.dedup();
+            assert_eq!(peers, in_units);
+        }
+        let peers: Vec<u8> = Sudoku::peers(0).skip(16).collect();
+        assert_eq!(peers, vec![10, 11, 19, 20]);
+        assert_eq!(Sudoku::units(40)[0], [36, 37, 38, 39, 41, 42, 43, 44]);
+    }
+
+    #[test]
+    fn test_full_units() {
+        let row: Vec<u8> = Sudoku::row_full(40).collect();
+        assert_eq!(row, vec![36, 37, 38, 39, 40, 41, 42, 43, 44]);
+        let column: Vec<u8> = Sudoku::column_full(40).collect();
+        assert_eq!(column, vec![4, 13, 22, 31, 40, 49, 58, 67, 76]);
+        let square: Vec<u8> = Sudoku::square_full(40).collect();
+        assert_eq!(square, vec![30, 31, 32, 39, 40, 41, 48, 49, 50]);
+
+        // The filtered ones only leave the cell out
+        for cell in 0..81 {
+            let mut row: Vec<u8> = Sudoku::row(cell).collect();
+            row.push(cell);
+            row.sort_unstable();
+            assert_eq!(row, Sudoku::row_full(cell).collect::<Vec<_>>());
+            let mut square: Vec<u8> = Sudoku::square(cell).collect();
+            square.push(cell);
+            square.sort_unstable();
+            assert_eq!(square, Sudoku::square_full(cell).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn test_square() {
+        let center: Vec<u8> = Sudoku::square(40).collect();
+        assert_eq!(center, vec![30, 31, 32, 39, 41, 48, 49, 50]);
+        assert_eq!(
+            Sudoku::square(0).collect::<Vec<_>>(),
+            vec![1, 2, 9, 10, 11, 18, 19, 20]
+        );
+
+        // The cells sharing both band and stack
+        for cell in 0..81u8 {
+            let mut expected: Vec<u8> = (0..81)
+                .filter(|&c| c != cell && c / 27 == cell / 27 && c % 9 / 3 == cell % 9 / 3)
+                .collect();
+            let mut square: Vec<u8> = Sudoku::square(cell).collect();
+            expected.sort_unstable();
+            square.sort_unstable();
+            assert_eq!(square, expected);
+        }
+    }
+
+    #[test]
+    fn test_is_solved() {
+        let mut sudoku = Sudoku::try_from(HARD).unwrap();
+        assert!(!sudoku.is_solved());
+        sudoku.solve();
+        assert!(sudoku.is_solved());
+
+        // Full, but two rows swapped across bands
+        let mut rows = sudoku.to_rows();
+        rows.swap(0, 3);
+        assert!(!Sudoku::from_rows(rows).unwrap().is_solved());
+
+        // Full, but a digit twice in a row, a column and a box
+        let mut twice = sudoku.clone();
+        twice.0[0] = twice.0[1];
+        assert!(!twice.is_solved());
+
+        // Valid, but not full
+        let mut missing = sudoku.clone();
+        missing.0[80] = 0;
+        assert!(!missing.is_solved());
+        assert!(!Sudoku::empty().is_solved());
+    }
+
+    /// A valid solution, with its digits relabeled
+    fn solutions() -> impl proptest::strategy::Strategy<Value = Sudoku> {
+        Just((1..=9).collect::<Vec<u8>>())
+            .prop_shuffle()
+            .prop_map(|digits| {
+                let mut sudoku = Sudoku::try_from(HARD).unwrap();
+                sudoku.solve();
+                for cell in sudoku.0.iter_mut() {
+                    *cell = digits[*cell as usize - 1];
+                }
+                sudoku
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn prop_to_line_round_trip(cells in prop::collection::vec(0..=9u8, 81)) {
+            let mut grid = [0; 81];
+            grid.copy_from_slice(&cells);
+            let sudoku = Sudoku(grid, History::default());
+            prop_assert_eq!(Sudoku::try_from(sudoku.to_line().as_str()), Ok(sudoku.clone()));
+            prop_assert_eq!(Sudoku::try_from(sudoku.to_string().as_str()), Ok(sudoku));
+        }
+
+        #[test]
+        fn prop_solve_is_solved(
+            solution in solutions(),
+            blanks in prop::collection::vec(any::<bool>(), 81),
+        ) {
+            let mut sudoku = solution.clone();
+            for (cell, &blank) in sudoku.0.iter_mut().zip(blanks.iter()) {
+                if blank {
+                    *cell = 0;
+                }
+            }
+            prop_assert!(sudoku.solve());
+            prop_assert!(sudoku.is_solved());
+        }
+    }
+}
@@ -1,309 +1,590 @@
-//! Sudoku solver
-//! Based on this excelent writeup <http://norvig.com/sudoku.html>
+//! Command line front end for the sudoku solver
 
 use std::convert::TryFrom;
-use std::fmt;
+use std::io::Write;
 use std::time;
 
-/// `Possible` stores all the possible values that can go on a square,
-/// from 1 to 9.
-#[derive(Copy, Clone, Debug, PartialEq)]
-struct Possible(u16);
+use sudoku_solver::{
+    puzzle_stream, solve_batch, Difficulty, SolveResult, SolveStats, SolveStatus, Solver, Sudoku,
+    Technique,
+};
+
+/// Prints a single puzzle, followed by its solution in `result` and
+/// the time it took.  An unsolvable puzzle is printed again as it was.
+/// Returns whether there was a solution.
+fn solve_and_print(sudoku: Sudoku, result: SolveResult) -> bool {
+    println!("{}", sudoku);
+    let t = result.elapsed.as_secs_f64();
+    let solved = result.solution.is_some();
+    println!(
+        "{}\n({:.6} seconds)\n",
+        result.solution.unwrap_or(sudoku),
+        t
+    );
+    solved
+}
 
-impl Possible {
-    fn new() -> Self {
-        // All 9 values are possible by default
-        Self(0x1FF)
-    }
+/// How many solutions `--count` and `--solutions` look for at most,
+/// unless `--max-solutions` says otherwise.  An almost empty grid has
+/// far too many to ever finish counting them.
+const MAX_SOLUTIONS: usize = 1000;
+
+/// Prints the puzzle and how many solutions it has, or that it has at
+/// least `max` of them.  Returns whether there was any.
+fn count_and_print(sudoku: &Sudoku, max: usize) -> bool {
+    println!("{}", sudoku);
+    let count = sudoku.solution_count_capped(max);
+    println!("{}\n", solution_total(count, max));
+    count > 0
+}
 
-    fn len(&self) -> u32 {
-        self.0.count_ones()
+/// Prints the puzzle, then its solutions, no more than `max` of them.
+/// Returns whether there was any.
+fn print_solutions(sudoku: &Sudoku, max: usize) -> bool {
+    println!("{}", sudoku);
+    let mut solutions = Vec::new();
+    let count = sudoku.solve_all_into(&mut solutions, max);
+    for grid in solutions {
+        // We can unwrap safely because the solver only makes valid grids
+        println!("{}", Sudoku::try_from(&grid[..]).unwrap());
     }
+    println!("{}\n", solution_total(count, max));
+    count > 0
+}
 
-    fn contains(&self, pos: u8) -> bool {
-        (1 << (pos - 1)) & self.0 != 0
+/// `(3 solutions)`, or `(at least 1000 solutions)` if the search
+/// stopped at `max`
+fn solution_total(count: usize, max: usize) -> String {
+    let plural = if count == 1 { "" } else { "s" };
+    if count >= max {
+        format!("(at least {} solution{})", count, plural)
+    } else {
+        format!("({} solution{})", count, plural)
     }
+}
 
-    fn remove(&self, pos: u8) -> Self {
-        // Although this is technically a flip we never add items to
-        // the bitset, only discard, hence the name.  A better version
-        // without cloning could reuse this method.
-        Self((1 << (pos - 1)) ^ self.0)
-    }
+/// What `--json` and `--csv` print about a puzzle
+struct Report {
+    puzzle: Sudoku,
+    solution: Option<Sudoku>,
+    unique: bool,
+    micros: u128,
+}
 
-    /// Returns an iterator over the values that are set
-    fn values(&self) -> impl Iterator<Item = u8> {
-        let mask = self.0;
-        (1..=9).filter(move |i| (1 << (i - 1)) & mask != 0)
+impl Report {
+    /// `puzzle` and how the solver did on it
+    fn new(puzzle: Sudoku, result: SolveResult) -> Self {
+        let solution = result.solution;
+        Report {
+            unique: solution.is_some() && puzzle.is_unique(),
+            solution,
+            puzzle,
+            micros: result.elapsed.as_micros(),
+        }
     }
 
-    /// Return the first value or a 0. Very useful if we already know
-    /// that there is only one value in the set.
-    fn n(&self) -> u8 {
-        self.values().next().unwrap_or(0)
+    /// A single line JSON object.  Lines only have digits and dots, so
+    /// there is nothing to escape.
+    fn to_json(&self) -> String {
+        let solution = match &self.solution {
+            Some(solution) => format!("\"{}\"", solution.to_line()),
+            None => "null".to_string(),
+        };
+        format!(
+            concat!(
+                "{{\"puzzle\": \"{}\", \"solution\": {}, ",
+                "\"solvable\": {}, \"unique\": {}, \"micros\": {}}}"
+            ),
+            self.puzzle.to_line(),
+            solution,
+            self.solution.is_some(),
+            self.unique,
+            self.micros
+        )
     }
-}
-
-/// `Values` stores all the possible values for every cell in the
-/// sudoku.  Its core is the search function, that uses constraint
-/// propagation and backtracking to find a possible solution to the
-/// sudoku.
-#[derive(Clone, Debug)]
-struct Values(Vec<Possible>);
 
-impl Values {
-    fn new() -> Self {
-        Values(vec![Possible::new(); 81])
+    /// A row under `CSV_HEADER`
+    fn to_csv(&self) -> String {
+        let solution = self.solution.as_ref().map(Sudoku::to_line);
+        let fields = [
+            self.puzzle.to_line(),
+            solution.unwrap_or_default(),
+            self.solution.is_some().to_string(),
+            self.unique.to_string(),
+            self.puzzle.clue_count().to_string(),
+            self.micros.to_string(),
+        ];
+        let fields: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        fields.join(",")
     }
+}
 
-    fn search(self) -> Option<Self> {
-        if self.0.iter().all(|p| p.len() == 1) {
-            // Already solved
-            return Some(self);
-        }
+const CSV_HEADER: &str = "puzzle,solution,solvable,unique,clues,micros";
 
-        // Find the first square with the least options
-        // This way the probability to correctly "guess" is higher
-        // If we later find that there was a contradiction, we removed
-        // the most of we can of the possibilities.
-
-        // We can unwrap safely because at least 1 such
-        // square exists
-        let (_, cell) = self
-            .0
-            .iter()
-            .enumerate()
-            .filter(|(_, p)| p.len() > 1)
-            .map(|(i, p)| (p.len(), i))
-            .min()
-            .unwrap();
-
-        // Return the first found solution (if any) while trying to assign
-        // the possible values for that cell
-        self.0[cell]
-            .values()
-            .filter_map(|n| self.clone().assign(n, cell)?.search())
-            .next()
+/// Quotes `field` if it has anything that needs it.  None of the fields
+/// should, but just in case.
+fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
     }
+}
 
-    fn assign(self, digit: u8, cell: usize) -> Option<Self> {
-        let mut values = self.clone();
+/// What `--stats` prints after the whole batch
+struct BatchStats {
+    /// The human techniques without guessing, to tell the puzzles
+    /// they solve
+    logic: Solver,
+    puzzles: usize,
+    unsolvable: usize,
+    /// Solved without guessing by the human techniques
+    logical: usize,
+    /// Indexed by `Difficulty`
+    difficulties: [usize; 4],
+    guesses: usize,
+}
 
-        // Eliminates all the other possibilities from this cell
-        for other_digit in self.0[cell].values().filter(|&d| d != digit) {
-            values = values.eliminate(other_digit, cell)?
+impl BatchStats {
+    fn new() -> Self {
+        BatchStats {
+            logic: Solver::new().with_techniques(&Technique::PIPELINE),
+            puzzles: 0,
+            unsolvable: 0,
+            logical: 0,
+            difficulties: [0; 4],
+            guesses: 0,
         }
-        Some(values)
     }
 
-    fn eliminate(self, digit: u8, cell: usize) -> Option<Self> {
-        let mut possibles = self.0[cell];
-
-        if !possibles.contains(digit) {
-            // Was already removed
-            return Some(self);
-        }
-
-        possibles = possibles.remove(digit);
-
-        let mut values = self.clone();
-        values.0[cell] = possibles;
-
-        match possibles.len() {
-            0 => {
-                // No possible values left: contradiction
-                return None;
-            }
-            1 => {
-                // If only one possibility left, eliminate it as a possibility
-                // from all its peers
-                let d = possibles.n();
-                for peer in Sudoku::peers(cell as u8) {
-                    values = values.eliminate(d, peer as usize)?
+    /// Counts `puzzle`, given how the search solved it
+    fn add(&mut self, puzzle: &Sudoku, result: &SolveResult) {
+        self.puzzles += 1;
+        match result.solution.as_ref().map(|_| result.stats) {
+            Some(stats) => {
+                self.difficulties[stats.difficulty() as usize] += 1;
+                self.guesses += stats.guesses;
+                if self.logic.solve(puzzle).status == SolveStatus::Solved {
+                    self.logical += 1;
                 }
             }
-            _ => {}
+            None => self.unsolvable += 1,
         }
+    }
 
-        // Check if for any unit, this digit can only appear in one
-        // cell, if so, assign it to that cell
-        for unit in Sudoku::units(cell as u8) {
-            let places_for_d: Vec<u8> = unit
-                .into_iter()
-                .filter(|&p| values.0[p as usize].contains(digit))
-                .collect();
-
-            match places_for_d.len() {
-                0 => return None,
-                1 => {
-                    values = values.assign(digit, places_for_d[0] as usize)?;
-                }
-                _ => {}
-            };
+    fn print(&self) {
+        let solved = self.puzzles - self.unsolvable;
+        println!("{:<16}{:>8}", "puzzles", self.puzzles);
+        println!("{:<16}{:>8}", "logic only", self.logical);
+        println!("{:<16}{:>8}", "guessing", solved - self.logical);
+        println!("{:<16}{:>8}", "unsolvable", self.unsolvable);
+        for (difficulty, count) in DIFFICULTIES.iter().zip(self.difficulties.iter()) {
+            println!("{:<16}{:>8}", difficulty.to_string(), count);
         }
-
-        Some(values)
+        let average = self.guesses as f64 / solved.max(1) as f64;
+        println!("{:<16}{:>8.1}", "average guesses", average);
     }
 }
 
-/// `Sudoku` contains a sudoku puzzle.
-/// Can parse from strings to puzzles and display itself.
-/// When calling solve, leverages to `Values::search`.
-struct Sudoku([u8; 81]);
-
-impl Sudoku {
-    fn solve(&mut self) -> bool {
-        let mut values = Values::new();
-        for (i, &v) in self.0.iter().enumerate().filter(|(_, &v)| v != 0) {
-            if let Some(v) = values.assign(v, i) {
-                values = v;
-            } else {
-                return false;
-            }
-        }
-
-        if let Some(values) = values.search() {
-            for (i, &v) in values.0.iter().enumerate() {
-                self.0[i] = v.n();
-            }
-            true
-        } else {
-            // We did not find a solution
-            false
-        }
+/// `Puzzle 7:` before the puzzle read in seventh place, with
+/// `--number`
+fn print_number(number: Option<usize>) {
+    if let Some(number) = number {
+        println!("Puzzle {}:", number);
     }
+}
 
-    /// Iterator containing the cell indices of the row in which
-    /// `cell` is
-    fn row(cell: u8) -> impl Iterator<Item = u8> {
-        let row = cell / 9;
-        (0..9).map(move |r| row * 9 + r).filter(move |&r| r != cell)
+/// Prints the puzzles sorted from easiest to hardest, together with
+/// their rating and number.  Unsolvable puzzles are printed last.
+fn print_sorted(mut report: Vec<(Option<usize>, Sudoku, Sudoku, Option<SolveStats>)>) {
+    // `None` sorts before any `Some`, so map it to the maximum
+    report.sort_by_key(|(_, _, _, stats)| stats.map_or(usize::MAX, |s| s.guesses));
+    for (number, puzzle, solution, stats) in report {
+        print_number(number);
+        println!("{}", puzzle);
+        match stats {
+            Some(stats) => println!(
+                "{}\n({}, {} guesses)\n",
+                solution,
+                stats.difficulty(),
+                stats.guesses
+            ),
+            None => println!("(unsolvable)\n"),
+        }
     }
+}
 
-    /// Iterator containing the cell indices of the column in which
-    /// `cell` is
-    fn column(cell: u8) -> impl Iterator<Item = u8> {
-        let column = cell % 9;
-
-        (0..9)
-            .map(move |c| c * 9 + column)
-            .filter(move |&c| c != cell)
+/// Solves the whole batch with `jobs` threads, then prints every
+/// puzzle with its number, followed by its solution, and the total
+/// time.  Returns whether they all had a solution.
+fn solve_and_print_batch(puzzles: Vec<Sudoku>, numbers: &[Option<usize>], jobs: usize) -> bool {
+    #[cfg(feature = "parallel")]
+    {
+        if let Err(e) = rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build_global()
+        {
+            eprintln!("could not start {} threads: {}", jobs, e);
+        }
     }
 
-    /// Iterator containing the cell indices of the square in which
-    /// `cell` is.
-    fn square(cell: u8) -> impl Iterator<Item = u8> {
-        let (row, column) = (cell / 9, cell % 9);
-        let (r, c) = (row / 3, column / 3);
-
-        (0..9)
-            .map(move |n| 3 * (9 * r + c + 3 * (n / 3)) + n % 3)
-            .filter(move |&t| t != cell)
+    #[cfg(not(feature = "parallel"))]
+    {
+        if jobs > 1 {
+            eprintln!("built without the parallel feature, using a single thread");
+        }
     }
 
-    fn units(i: u8) -> Vec<Vec<u8>> {
-        vec![
-            Sudoku::row(i).collect(),
-            Sudoku::column(i).collect(),
-            Sudoku::square(i).collect(),
-        ]
+    let t0 = time::Instant::now();
+    let solutions = solve_batch(puzzles.clone());
+    let t = t0.elapsed().as_secs_f64();
+
+    let mut all_solved = true;
+    for ((puzzle, solution), &number) in puzzles.iter().zip(solutions).zip(numbers) {
+        print_number(number);
+        println!("{}", puzzle);
+        match solution {
+            Some(solution) => println!("{}", solution),
+            None => {
+                println!("(unsolvable)\n");
+                all_solved = false;
+            }
+        }
     }
+    println!("({} puzzles in {:.6} seconds)", puzzles.len(), t);
+    all_solved
+}
 
-    fn peers(i: u8) -> impl Iterator<Item = u8> {
-        Sudoku::row(i)
-            .chain(Sudoku::column(i))
-            .chain(Sudoku::square(i))
+const DIFFICULTIES: [Difficulty; 4] = [
+    Difficulty::Easy,
+    Difficulty::Medium,
+    Difficulty::Hard,
+    Difficulty::Fiendish,
+];
+
+/// Prints `count` new puzzles of `difficulty`, one per line
+fn print_generated(count: usize, difficulty: Difficulty) {
+    // A different batch every time
+    let seed = time::SystemTime::now()
+        .duration_since(time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64);
+    for puzzle in puzzle_stream(seed, difficulty).take(count) {
+        println!("{}", puzzle.to_line());
     }
 }
 
-impl fmt::Display for Sudoku {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut buffer = String::new();
-        for (i, &n) in self.0.iter().enumerate() {
-            if i != 0 && i % 9 == 0 {
-                buffer.push_str("|\n");
-            }
+const REPL_HELP: &str = "\
+Type a puzzle in a single line to work on it, or one of:
+    solve    print its solution
+    count    print how many solutions it has
+    hint     fill in a cell that constraint propagation alone decides
+    help     print this message
+    quit     leave, as does the end of the input
+";
+
+/// Reads puzzles and commands from stdin, one per line, answering each
+/// one before showing the prompt again
+fn repl() {
+    let mut current: Option<Sudoku> = None;
+    let mut line = String::new();
+    loop {
+        print!("> ");
+        // Nothing to do about a broken stdout, the next print fails
+        let _ = std::io::stdout().flush();
+        line.clear();
+        match std::io::stdin().read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
 
-            if i % 27 == 0 {
-                buffer.push_str("+------+------+------+\n");
+        let command = line.trim();
+        match (command, &mut current) {
+            ("", _) => {}
+            ("quit", _) | ("exit", _) => break,
+            ("help", _) => print!("{}", REPL_HELP),
+            ("solve", Some(sudoku)) => {
+                let mut solution = sudoku.clone();
+                if solution.solve() {
+                    println!("{}", solution);
+                } else {
+                    println!("(unsolvable)");
+                }
             }
-
-            if i % 3 == 0 {
-                buffer.push_str("|");
+            ("count", Some(sudoku)) => {
+                let count = sudoku.solution_count_capped(MAX_SOLUTIONS);
+                println!("{}", solution_total(count, MAX_SOLUTIONS));
             }
-
-            if n != 0 {
-                buffer.push_str(&(n.to_string() + " "));
-            } else {
-                buffer.push_str(". ");
+            ("hint", Some(sudoku)) => match sudoku.naked_singles().first() {
+                Some(&(cell, digit)) => {
+                    println!(
+                        "row {}, column {} is a {}",
+                        cell / 9 + 1,
+                        cell % 9 + 1,
+                        digit
+                    );
+                    let mut grid = sudoku.to_rows();
+                    grid[cell / 9][cell % 9] = digit;
+                    *sudoku = Sudoku::from(grid);
+                    println!("{}", sudoku);
+                }
+                None => println!("no cell can be decided without guessing"),
+            },
+            ("solve", None) | ("count", None) | ("hint", None) => {
+                println!("type a puzzle first")
             }
+            (puzzle, _) => match Sudoku::try_from(puzzle) {
+                Ok(sudoku) => {
+                    println!("{}", sudoku);
+                    current = Some(sudoku);
+                }
+                Err(e) => println!("not a command nor a puzzle: {}", e),
+            },
         }
-        buffer.push_str("|\n+------+------+------+\n");
-        write!(f, "{}", buffer)
     }
 }
 
-impl TryFrom<&str> for Sudoku {
-    type Error = &'static str;
-
-    /// We expect to read 81 grid data between digits and `.`s.
-    /// A dot (`.`) or a `0` means that that particular cell is empty.
-    /// All other non-digit values are ignored.
-    /// If a grid can not be read, an Err is returned.
-    fn try_from(value: &str) -> Result<Self, Self::Error> {
-        let mut grid = [0u8; 81];
-
-        let mut i = 0;
-        for c in value.chars() {
-            if i > 80 {
-                // No need to read more
-                break;
-            }
-
-            if c == '.' {
-                // We leave the 0 in place and count it as a digit
-                i += 1;
-                continue;
-            }
+const USAGE: &str = "\
+Usage: sudoku_solver [OPTIONS] < PUZZLES
+
+Reads sudoku puzzles from stdin, separated by empty lines, and prints
+their solutions.  Only digits and dots are read from a puzzle: both a 0
+and a dot (. or ·) are an empty cell, and everything else is ignored,
+so all of these are the same puzzle:
+
+    4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......
+
+    4 . . |. . . |8 . 5
+    . 3 . |. . . |. . .
+    ...
+
+Options:
+    --candidates          print the possible values of every cell after
+                          constraint propagation instead of solving
+    --sort-by-difficulty  print the whole batch at the end, from the
+                          easiest puzzle to the hardest one
+    --strict              fail on puzzles with more than 81 cells
+    --json                print a JSON object per line for every puzzle
+                          instead of the grids
+    --csv                 print a CSV row for every puzzle instead of the
+                          grids, after a header
+    --input-format=csv    read 81 numbers separated by commas or spaces
+                          instead, with a 0 for the empty cells
+    --input-format=dense  read 81 digits and dots, the default
+    --count               print how many solutions every puzzle has
+                          instead of solving it
+    --solutions           print every solution of every puzzle
+    --max-solutions N     stop counting or printing solutions after N
+                          of them (1000 by default)
+    --number              print the position of every puzzle in the
+                          input before it, like Puzzle 7:
+    --stats               print a summary of the whole batch at the end:
+                          how many puzzles needed guessing, were
+                          unsolvable or of every difficulty, and the
+                          average number of guesses
+    --repl                work on one puzzle at a time, typing commands
+                          to solve it, count its solutions or get hints
+    --generate N          print N new puzzles, one per line, instead of
+                          reading any
+    --difficulty NAME     how hard the generated puzzles are: easy,
+                          medium (the default), hard or fiendish
+    --jobs N              solve the whole batch at once using N threads
+                          (needs the parallel feature for N > 1)
+    --help                print this message
+    --version             print the version
+
+Exit status:
+    0  every puzzle was solved
+    1  some puzzle has no solution
+    2  some puzzle could not be read, the others were still solved
+    3  the options were wrong, or unknown
+";
+
+/// Exit codes, as listed in `USAGE`.  When there are both unsolvable
+/// and malformed puzzles the highest one wins.
+const EXIT_UNSOLVABLE: i32 = 1;
+const EXIT_MALFORMED: i32 = 2;
+const EXIT_USAGE: i32 = 3;
+
+/// Command line flags.  An unknown one is a usage error, so a typo
+/// doesn't go unnoticed.
+#[derive(Default)]
+struct Options {
+    candidates: bool,
+    sort_by_difficulty: bool,
+    strict: bool,
+    csv_input: bool,
+    json: bool,
+    csv: bool,
+    stats: bool,
+    number: bool,
+    count: bool,
+    solutions: bool,
+    max_solutions: Option<usize>,
+    jobs: Option<usize>,
+    repl: bool,
+    generate: Option<usize>,
+    difficulty: Option<Difficulty>,
+    help: bool,
+    version: bool,
+}
 
-            if let Some(d) = c.to_digit(10) {
-                // If parsing the digit fails (and is not a `.`), we
-                // ignore it
-                grid[i] = d as u8;
-                i += 1;
+impl Options {
+    fn from_args() -> Result<Self, String> {
+        let mut options = Options::default();
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_ref() {
+                "--candidates" => options.candidates = true,
+                "--sort-by-difficulty" => options.sort_by_difficulty = true,
+                "--strict" => options.strict = true,
+                "--json" => options.json = true,
+                "--csv" => options.csv = true,
+                "--stats" => options.stats = true,
+                "--number" => options.number = true,
+                "--count" => options.count = true,
+                "--solutions" => options.solutions = true,
+                "--max-solutions" => {
+                    options.max_solutions = Some(parse_max_solutions(args.next())?)
+                }
+                "--repl" => options.repl = true,
+                "--generate" => options.generate = Some(parse_generate(args.next())?),
+                "--difficulty" => options.difficulty = Some(parse_difficulty(args.next())?),
+                "--jobs" | "-j" => options.jobs = Some(parse_jobs(args.next())?),
+                "--help" | "-h" => options.help = true,
+                "--version" | "-V" => options.version = true,
+                "--input-format=csv" => options.csv_input = true,
+                "--input-format=dense" => options.csv_input = false,
+                _ => {
+                    if let Some(jobs) = arg.strip_prefix("--jobs=") {
+                        options.jobs = Some(parse_jobs(Some(jobs.to_string()))?);
+                    } else if let Some(count) = arg.strip_prefix("--generate=") {
+                        options.generate = Some(parse_generate(Some(count.to_string()))?);
+                    } else if let Some(name) = arg.strip_prefix("--difficulty=") {
+                        options.difficulty = Some(parse_difficulty(Some(name.to_string()))?);
+                    } else if let Some(max) = arg.strip_prefix("--max-solutions=") {
+                        options.max_solutions = Some(parse_max_solutions(Some(max.to_string()))?);
+                    } else if let Some(format) = arg.strip_prefix("--input-format=") {
+                        return Err(format!("unknown input format {}", format));
+                    } else if arg.starts_with('-') {
+                        return Err(format!("unknown option {}", arg));
+                    }
+                }
             }
         }
-
-        if i == 81 {
-            Ok(Self(grid))
-        } else {
-            Err("malformed grid")
-        }
+        Ok(options)
     }
 }
 
-#[cfg(test)]
-mod test {
-    use super::*;
+fn parse_jobs(value: Option<String>) -> Result<usize, String> {
+    match value.as_deref().map(str::parse) {
+        Some(Ok(jobs)) if jobs > 0 => Ok(jobs),
+        _ => Err("--jobs needs a number of threads greater than 0".to_string()),
+    }
+}
 
-    #[test]
-    fn test_possible() {
-        assert_eq!(Possible(0x3).len(), 2);
-        assert_eq!(Possible(0x6).len(), 2);
-        assert_eq!(Possible(0x1).len(), 1);
-        assert_eq!(Possible::new().len(), 9);
+fn parse_generate(value: Option<String>) -> Result<usize, String> {
+    match value.as_deref().map(str::parse) {
+        Some(Ok(count)) => Ok(count),
+        _ => Err("--generate needs a number of puzzles".to_string()),
+    }
+}
 
-        assert_eq!(Possible(0x6).contains(3), true);
-        assert_eq!(Possible(0x6).contains(1), false);
-        assert_eq!(Possible(0x8).contains(8), false);
+fn parse_difficulty(value: Option<String>) -> Result<Difficulty, String> {
+    let name = value.unwrap_or_default();
+    DIFFICULTIES
+        .iter()
+        .copied()
+        .find(|d| d.to_string() == name)
+        .ok_or_else(|| format!("unknown difficulty {}", name))
+}
 
-        assert_eq!(Possible(0x8).remove(4), Possible(0x0));
-        assert_eq!(Possible(0xF).remove(4), Possible(0x7));
+fn parse_max_solutions(value: Option<String>) -> Result<usize, String> {
+    match value.as_deref().map(str::parse) {
+        Some(Ok(max)) if max > 0 => Ok(max),
+        _ => Err("--max-solutions needs a number greater than 0".to_string()),
     }
 }
 
-/// Read puzzles from stdin separated by an empty line, and solve them
-fn main() -> Result<(), &'static str> {
+/// Read puzzles from stdin separated by an empty line, and solve them.
+///
+/// With `--candidates` puzzles are not solved, instead the possible
+/// values of every cell after constraint propagation are printed.
+///
+/// With `--strict` a puzzle with more than 81 cells is an error instead
+/// of ignoring whatever follows the 81st cell.
+///
+/// With `--input-format=csv` puzzles are read with `Sudoku::parse_csv`,
+/// always strictly.
+///
+/// With `--json` every puzzle is printed as soon as it is solved, as a
+/// JSON object with its solution, whether it is unique, and the time it
+/// took, instead of as grids.  `--csv` prints the same as CSV rows,
+/// with the number of clues too, and a header before the first one.
+///
+/// With `--count` the solutions of every puzzle are counted instead,
+/// and with `--solutions` all of them are printed.  Both stop after
+/// `--max-solutions`, a thousand by default, so they always finish.
+///
+/// With `--number` every puzzle is printed after its position in the
+/// input, counting from 1 and including the ones that can't be read.
+/// `--json` and `--csv` print the puzzle itself, so no number.
+///
+/// With `--stats` every puzzle is rated on top of whatever else is
+/// done with it, and a summary is printed at the end.
+///
+/// With `--repl` there is no batch: a prompt reads a puzzle or a
+/// command per line, see `REPL_HELP`.
+///
+/// With `--generate` nothing is read, new puzzles are printed instead,
+/// of `--difficulty` or medium ones.
+///
+/// With `--jobs` the whole batch is kept in memory too, and solved at
+/// once when stdin is exhausted.
+///
+/// With `--sort-by-difficulty` the whole batch is kept in memory (the
+/// puzzle and its solution, a couple hundred bytes each) and nothing
+/// is printed until stdin is exhausted.  Without it every puzzle is
+/// printed as soon as it is solved.
+///
+/// A puzzle that can't be read is reported on stderr and skipped.  The
+/// exit status tells scripts whether that happened, or whether some
+/// puzzle had no solution, see `USAGE`.  `--candidates` doesn't solve
+/// anything, so only malformed puzzles change its exit status.
+fn main() {
+    let options = Options::from_args().unwrap_or_else(|e| {
+        eprintln!("{}\n\n{}", e, USAGE);
+        std::process::exit(EXIT_USAGE);
+    });
+    if options.help {
+        print!("{}", USAGE);
+        return;
+    }
+    if options.version {
+        println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+        return;
+    }
+    if options.repl {
+        repl();
+        return;
+    }
+    if let Some(count) = options.generate {
+        print_generated(count, options.difficulty.unwrap_or(Difficulty::Medium));
+        return;
+    }
+
+    if options.csv && !options.json {
+        println!("{}", CSV_HEADER);
+    }
+
+    let mut solver = Solver::new();
+    let mut report = Vec::new();
+    let mut stats = BatchStats::new();
+    let mut batch = Vec::new();
+    let mut batch_numbers = Vec::new();
+    let mut read = 0;
+    let mut unsolvable = false;
+    let mut malformed = false;
+
     let mut buff = String::new();
     let mut puzzle = String::new();
     while let Ok(n) = std::io::stdin().read_line(&mut buff) {
@@ -313,19 +594,90 @@ fn main() -> Result<(), &'static str> {
         }
 
         if buff.trim().is_empty() && !puzzle.trim().is_empty() {
-            let mut sudoku = Sudoku::try_from(puzzle.as_ref())?;
-            println!("{}", sudoku);
-            let t0 = time::Instant::now();
-            sudoku.solve();
-            let dur = time::Instant::now() - t0;
-            let t = dur.as_secs() as f64 + dur.subsec_micros() as f64 * 1e-6;
-            println!("{}\n({:.6} seconds)\n", sudoku, t);
+            read += 1;
+            let number = Some(read).filter(|_| options.number);
+            let parsed = if options.csv_input {
+                Sudoku::parse_csv(&puzzle)
+            } else if options.strict {
+                Sudoku::parse_strict(&puzzle)
+            } else {
+                Sudoku::try_from(puzzle.as_str())
+            };
             puzzle.clear();
+            let sudoku = match parsed {
+                Ok(sudoku) => sudoku,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    malformed = true;
+                    buff.clear();
+                    continue;
+                }
+            };
+            // Solved once for the stats and whatever prints the solution
+            let result = if options.stats {
+                let result = solver.solve(&sudoku);
+                stats.add(&sudoku, &result);
+                Some(result)
+            } else {
+                None
+            };
+            let solve = || result.unwrap_or_else(|| solver.solve(&sudoku));
+            let max_solutions = options.max_solutions.unwrap_or(MAX_SOLUTIONS);
+            let solved = if options.candidates {
+                print_number(number);
+                println!("{}\n{}", sudoku, sudoku.candidates_display());
+                true
+            } else if options.count {
+                print_number(number);
+                count_and_print(&sudoku, max_solutions)
+            } else if options.solutions {
+                print_number(number);
+                print_solutions(&sudoku, max_solutions)
+            } else if options.json || options.csv {
+                let result = solve();
+                let report = Report::new(sudoku, result);
+                if options.json {
+                    println!("{}", report.to_json());
+                } else {
+                    println!("{}", report.to_csv());
+                }
+                report.solution.is_some()
+            } else if options.sort_by_difficulty {
+                let result = solve();
+                let stats = result.solution.as_ref().map(|_| result.stats);
+                let solution = result.solution.unwrap_or_else(|| sudoku.clone());
+                report.push((number, sudoku, solution, stats));
+                stats.is_some()
+            } else if options.jobs.is_some() {
+                batch.push(sudoku);
+                batch_numbers.push(number);
+                true
+            } else {
+                print_number(number);
+                let result = solve();
+                solve_and_print(sudoku, result)
+            };
+            unsolvable |= !solved;
         } else {
             puzzle.push_str(&buff);
         }
         buff.clear();
     }
 
-    Ok(())
+    if options.sort_by_difficulty {
+        print_sorted(report);
+    }
+    if let Some(jobs) = options.jobs {
+        unsolvable |= !solve_and_print_batch(batch, &batch_numbers, jobs);
+    }
+    if options.stats {
+        stats.print();
+    }
+
+    if malformed {
+        std::process::exit(EXIT_MALFORMED);
+    }
+    if unsolvable {
+        std::process::exit(EXIT_UNSOLVABLE);
+    }
 }
@@ -0,0 +1,282 @@
+//! Bitset of candidate values for a cell
+
+use std::fmt;
+
+/// The integer behind a `Possible`, one bit per value.  Implemented
+/// for `u16`, `u32` and `u64`, so bigger boards can have more values.
+pub trait BitSet: Copy + fmt::Debug + PartialEq {
+    /// How many values fit
+    const CAPACITY: u8;
+
+    /// The highest value a `Possible` takes, at most `CAPACITY`.  Any
+    /// other one is ignored.
+    const MAX_VALUE: u8 = Self::CAPACITY;
+
+    /// The `n` lowest bits set
+    fn first(n: u8) -> Self;
+
+    fn count(self) -> u32;
+
+    /// Whether bit `i`, starting from 0, is set
+    fn get(self, i: u8) -> bool;
+
+    /// Flips bit `i`, starting from 0
+    fn flip(self, i: u8) -> Self;
+
+    /// The lowest bit set, if any
+    fn lowest(self) -> Option<u8>;
+}
+
+macro_rules! impl_bitset {
+    ($($t:ty => $max:expr),*) => {$(
+        impl BitSet for $t {
+            const CAPACITY: u8 = <$t>::BITS as u8;
+            const MAX_VALUE: u8 = $max;
+
+            fn first(n: u8) -> Self {
+                if n >= Self::CAPACITY {
+                    !0
+                } else {
+                    (1 << n) - 1
+                }
+            }
+
+            fn count(self) -> u32 {
+                self.count_ones()
+            }
+
+            fn get(self, i: u8) -> bool {
+                (1 << i) & self != 0
+            }
+
+            fn flip(self, i: u8) -> Self {
+                (1 << i) ^ self
+            }
+
+            fn lowest(self) -> Option<u8> {
+                if self == 0 {
+                    None
+                } else {
+                    Some(self.trailing_zeros() as u8)
+                }
+            }
+        }
+    )*};
+}
+
+// `u16` is the default for the usual board, so only 1 to 9 are values
+impl_bitset!(u16 => 9, u32 => 32, u64 => 64);
+
+/// `Possible` stores all the possible values that can go on a square,
+/// from 1 to 9 with the default `u16`, or up to the capacity of `B`
+/// for bigger boards.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Possible<B: BitSet = u16>(pub(crate) B);
+
+impl<B: BitSet> Possible<B> {
+    /// Values from 1 to `n` are possible
+    pub fn full(n: u8) -> Self {
+        Self(B::first(n))
+    }
+
+    pub fn len(&self) -> u32 {
+        self.0.count()
+    }
+
+    /// No value is possible, meaning a contradiction was found
+    pub fn is_empty(&self) -> bool {
+        self.0.lowest().is_none()
+    }
+
+    /// Whether `pos` is possible.  Never for 0 or a value over
+    /// `B::MAX_VALUE`.
+    pub fn contains(&self, pos: u8) -> bool {
+        Self::bit(pos).is_some_and(|i| self.0.get(i))
+    }
+
+    /// The same values but `pos`.  Nothing changes if it was not
+    /// there, including for 0 or a value over `B::MAX_VALUE`.
+    pub fn remove(&self, pos: u8) -> Self {
+        if self.contains(pos) {
+            Self(self.0.flip(pos - 1))
+        } else {
+            *self
+        }
+    }
+
+    /// Adds `pos` if it is not there, and removes it otherwise.
+    /// Nothing changes for 0 or a value over `B::MAX_VALUE`.
+    pub fn toggle(&self, pos: u8) -> Self {
+        match Self::bit(pos) {
+            Some(i) => Self(self.0.flip(i)),
+            None => *self,
+        }
+    }
+
+    /// The bit of `pos`, if it has one
+    fn bit(pos: u8) -> Option<u8> {
+        if pos == 0 || pos > B::MAX_VALUE {
+            None
+        } else {
+            Some(pos - 1)
+        }
+    }
+
+    /// Returns an iterator over the values that are set
+    pub fn values(&self) -> impl Iterator<Item = u8> {
+        let mut mask = self.0;
+        std::iter::from_fn(move || {
+            let i = mask.lowest()?;
+            mask = mask.flip(i);
+            Some(i + 1)
+        })
+    }
+
+    /// Return the first value or a 0. Very useful if we already know
+    /// that there is only one value in the set.
+    pub fn n(&self) -> u8 {
+        self.0.lowest().map_or(0, |i| i + 1)
+    }
+}
+
+/// The values between braces, like `{1,3,7}`
+impl<B: BitSet> fmt::Display for Possible<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let values: Vec<String> = self.values().map(|v| v.to_string()).collect();
+        write!(f, "{{{}}}", values.join(","))
+    }
+}
+
+/// The usual 9x9 board
+impl Possible {
+    pub fn new() -> Self {
+        // All 9 values are possible by default
+        Self::full(9)
+    }
+
+    /// The values set in `mask`, bit 0 for a 1 up to bit 8 for a 9, as
+    /// other bitset based solvers store them.  `None` if any higher bit
+    /// is set.
+    pub fn from_mask(mask: u16) -> Option<Self> {
+        if mask < 1 << 9 {
+            Some(Self(mask))
+        } else {
+            None
+        }
+    }
+
+    /// The mask `Possible::from_mask` takes
+    pub fn bits(&self) -> u16 {
+        self.0
+    }
+
+    /// Returns an iterator over the values that are not set, the
+    /// complement of `values`
+    pub fn missing(&self) -> impl Iterator<Item = u8> {
+        let p = *self;
+        (1..=9).filter(move |&i| !p.contains(i))
+    }
+
+    /// Return the first value that is not set or a 0 if all of them
+    /// are.
+    pub fn first_unset(&self) -> u8 {
+        self.missing().next().unwrap_or(0)
+    }
+}
+
+impl Default for Possible {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[allow(clippy::bool_assert_comparison)]
+    fn test_possible() {
+        assert_eq!(Possible(0x3u16).len(), 2);
+        assert_eq!(Possible(0x6u16).len(), 2);
+        assert_eq!(Possible(0x1u16).len(), 1);
+        assert_eq!(Possible::new().len(), 9);
+
+        assert_eq!(Possible(0x6u16).contains(3), true);
+        assert_eq!(Possible(0x6u16).contains(1), false);
+        assert_eq!(Possible(0x8u16).contains(8), false);
+
+        assert_eq!(Possible(0x8u16).remove(4), Possible(0x0));
+        assert_eq!(Possible(0xFu16).remove(4), Possible(0x7));
+    }
+
+    #[test]
+    fn test_possible_missing() {
+        // 1, 2, 5, 7 and 9 are set
+        let p = Possible(0b1_0101_0011u16);
+        assert_eq!(p.missing().collect::<Vec<_>>(), vec![3, 4, 6, 8]);
+        assert_eq!(p.first_unset(), 3);
+        assert_eq!(p.missing().count() as u32 + p.len(), 9);
+
+        assert_eq!(Possible::new().missing().count(), 0);
+        assert_eq!(Possible::new().first_unset(), 0);
+    }
+
+    #[test]
+    fn test_possible_mask() {
+        for mask in 0..1 << 9 {
+            assert_eq!(Possible::from_mask(mask).unwrap().bits(), mask);
+        }
+        assert_eq!(Possible::from_mask(0x1FF), Some(Possible::new()));
+        assert_eq!(Possible::from_mask(0x45).unwrap().to_string(), "{1,3,7}");
+        assert!(Possible::from_mask(0).unwrap().is_empty());
+        assert_eq!(Possible::from_mask(1 << 9), None);
+        assert_eq!(Possible::from_mask(u16::MAX), None);
+    }
+
+    #[test]
+    fn test_possible_u32() {
+        let p: Possible<u32> = Possible::full(16);
+        assert_eq!(p.len(), 16);
+        assert!(p.contains(16));
+        assert!(!p.contains(17));
+
+        let p = p.remove(1).remove(16);
+        assert_eq!(p.n(), 2);
+        assert_eq!(p.values().collect::<Vec<_>>(), (2..=15).collect::<Vec<_>>());
+        assert_eq!(Possible::<u32>::full(32).len(), 32);
+        assert!(Possible::<u64>::full(0).is_empty());
+    }
+
+    #[test]
+    fn test_possible_display() {
+        assert_eq!(Possible(0x45u16).to_string(), "{1,3,7}");
+        assert_eq!(Possible::new().to_string(), "{1,2,3,4,5,6,7,8,9}");
+        assert_eq!(Possible(0u16).to_string(), "{}");
+        assert_eq!(Possible(1u32 << 11).to_string(), "{12}");
+        assert_eq!(format!("{:?}", Possible(0x45u16)), "Possible(69)");
+    }
+
+    #[test]
+    fn test_possible_out_of_range() {
+        let p = Possible::new();
+        assert!(!p.contains(0));
+        assert!(!p.contains(10));
+        assert!(!p.contains(255));
+        assert_eq!(p.remove(0), p);
+        assert_eq!(p.remove(10), p);
+        assert_eq!(p.toggle(0), p);
+        assert_eq!(p.toggle(17), p);
+        // Not even inside the bitset, over 9
+        assert_eq!(p.toggle(10), p);
+        assert!(!Possible(1u16 << 11).contains(12));
+
+        let wide: Possible<u64> = Possible::full(64);
+        assert!(wide.contains(64));
+        assert!(!wide.contains(65));
+        assert_eq!(wide.remove(65), wide);
+
+        // Removing a value that is not there doesn't add it
+        assert_eq!(Possible(0x6u16).remove(1), Possible(0x6));
+    }
+}
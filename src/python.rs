@@ -0,0 +1,94 @@
+//! Python bindings, enabled with the `python` feature.
+//!
+//! The extension module is built with
+//!
+//! ```sh
+//! PYO3_BUILD_EXTENSION_MODULE=1 cargo rustc --release --lib \
+//!     --features python --crate-type cdylib
+//! ```
+//!
+//! and copying `target/release/libsudoku_solver.so` as
+//! `sudoku_solver.so` (`.pyd` on Windows) somewhere in the Python path,
+//! or with `maturin`.  It has a single class:
+//!
+//! ```python
+//! from sudoku_solver import Sudoku
+//!
+//! sudoku = Sudoku.from_string("4.....8.5.3...")
+//! if sudoku.solve():
+//!     print(sudoku.to_string())
+//! ```
+//!
+//! Malformed puzzles raise `ValueError`.
+
+use std::convert::TryFrom;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::{generate, GenerateOptions, Sudoku, SudokuError};
+
+impl From<SudokuError> for PyErr {
+    fn from(e: SudokuError) -> Self {
+        PyValueError::new_err(e.to_string())
+    }
+}
+
+/// A sudoku puzzle
+#[pyclass(name = "Sudoku", module = "sudoku_solver", from_py_object)]
+#[derive(Clone)]
+struct PySudoku(Sudoku);
+
+#[pymethods]
+impl PySudoku {
+    /// Reads a puzzle: 81 digits or dots, where both a 0 and a dot
+    /// are an empty cell, and anything else is ignored
+    #[staticmethod]
+    fn from_string(puzzle: &str) -> PyResult<Self> {
+        Ok(PySudoku(Sudoku::try_from(puzzle)?))
+    }
+
+    /// A random puzzle with a unique solution, reproducible when a
+    /// seed is given
+    #[staticmethod]
+    #[pyo3(signature = (seed=None))]
+    fn generate(seed: Option<u64>) -> Self {
+        let mut rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        // Without options there is always a puzzle
+        PySudoku(generate(&mut rng, GenerateOptions::default()).unwrap().0)
+    }
+
+    /// Solves the puzzle in place, returns whether there was a solution
+    fn solve(&mut self, py: Python<'_>) -> bool {
+        // Other Python threads can run meanwhile
+        py.detach(|| self.0.solve())
+    }
+
+    fn is_unique(&self, py: Python<'_>) -> bool {
+        py.detach(|| self.0.is_unique())
+    }
+
+    /// The 81 cells in a single line, with a dot for the empty ones
+    #[pyo3(name = "to_string")]
+    fn to_line(&self) -> String {
+        self.0.to_line()
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Sudoku.from_string('{}')", self.0.to_line())
+    }
+}
+
+#[pymodule]
+fn sudoku_solver(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PySudoku>()
+}
@@ -0,0 +1,296 @@
+//! Samurai sudoku: five 9x9 grids, one in the middle sharing each of
+//! its corner boxes with another grid.
+//!
+//! Every grid is a `Sudoku` with its own `Values`, propagated by the
+//! same engine as a single grid.  After every change the shared boxes
+//! are synchronized: a digit ruled out of a shared cell in one grid is
+//! ruled out of it in the other grid too, so the propagation crosses
+//! from one grid to the other.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{History, Sudoku, SudokuError, Values};
+
+/// The grids fit in a 21x21 square, leaving gaps between the corner
+/// grids
+const SIDE: usize = 21;
+
+/// Top left corner of every grid in the square, row and column: the
+/// four corners first, then the middle one
+const CORNERS: [(usize, usize); 5] = [(0, 0), (0, 12), (12, 0), (12, 12), (6, 6)];
+
+/// The middle grid is the last one
+const MIDDLE: usize = 4;
+
+/// The box every corner grid shares with the middle one, and that box
+/// in the middle grid, numbered row by row
+const SHARED: [(u8, u8); 4] = [(8, 0), (6, 2), (2, 6), (0, 8)];
+
+/// A samurai sudoku, with `0` for the empty cells.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Samurai {
+    /// Top left, top right, bottom left, bottom right and the middle
+    /// one, the shared cells have the same digit in both grids
+    grids: [Sudoku; 5],
+}
+
+impl Samurai {
+    /// For every spot of the 21x21 square, the grids that have a cell
+    /// there and that cell, none in the gaps
+    fn index() -> Vec<Vec<(usize, usize)>> {
+        (0..SIDE * SIDE)
+            .map(|p| {
+                let (r, c) = (p / SIDE, p % SIDE);
+                CORNERS
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &(top, left))| {
+                        (top..top + 9).contains(&r) && (left..left + 9).contains(&c)
+                    })
+                    .map(|(grid, &(top, left))| (grid, (r - top) * 9 + c - left))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Solves the puzzle in place.  Returns false if there is no
+    /// solution, leaving the puzzle untouched.
+    pub fn solve(&mut self) -> bool {
+        match self.values().and_then(search) {
+            Some(values) => {
+                for (grid, values) in self.grids.iter_mut().zip(values.iter()) {
+                    for (cell, p) in grid.0.iter_mut().zip(values.0.iter()) {
+                        *cell = p.n();
+                    }
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether the puzzle has one solution and only one.
+    pub fn is_unique(&self) -> bool {
+        self.values().map_or(0, |values| count_solutions(values, 2)) == 1
+    }
+
+    /// The five grids: top left, top right, bottom left, bottom right
+    /// and the middle one.
+    pub fn grids(&self) -> [Sudoku; 5] {
+        self.grids.clone()
+    }
+
+    /// The possible values of every grid, with the shared boxes
+    /// synchronized.  `None` if the clues are already contradictory.
+    fn values(&self) -> Option<[Values; 5]> {
+        let mut values = [Values::new(); 5];
+        for (values, grid) in values.iter_mut().zip(self.grids.iter()) {
+            *values = Values::from_grid(&grid.0)?;
+        }
+        synchronize(values)
+    }
+}
+
+/// Rules out of every shared cell the digits ruled out of it in the
+/// other grid, until both grids agree on all of them.  `None` on a
+/// contradiction.
+fn synchronize(mut values: [Values; 5]) -> Option<[Values; 5]> {
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (corner, &(here, there)) in SHARED.iter().enumerate() {
+            let (here, there) = (Sudoku::box_cells(here), Sudoku::box_cells(there));
+            for (&a, &b) in here.iter().zip(there.iter()) {
+                let (a, b) = (a as usize, b as usize);
+                let (pa, pb) = (values[corner].0[a], values[MIDDLE].0[b]);
+                for d in pa.values().filter(|&d| !pb.contains(d)) {
+                    values[corner] = values[corner].eliminate(d, a).ok()?;
+                    changed = true;
+                }
+                for d in pb.values().filter(|&d| !pa.contains(d)) {
+                    values[MIDDLE] = values[MIDDLE].eliminate(d, b).ok()?;
+                    changed = true;
+                }
+            }
+        }
+    }
+    Some(values)
+}
+
+/// The grid and cell with the fewest possibilities left, but more
+/// than one.  `None` once every cell is decided.
+fn most_constrained(values: &[Values; 5]) -> Option<(usize, usize)> {
+    let (_, grid, cell) = values
+        .iter()
+        .enumerate()
+        .flat_map(|(g, v)| v.0.iter().enumerate().map(move |(c, p)| (p.len(), g, c)))
+        .filter(|&(len, _, _)| len > 1)
+        .min()?;
+    Some((grid, cell))
+}
+
+/// Searches for a solution, guessing on the most constrained cell of
+/// any grid
+fn search(values: [Values; 5]) -> Option<[Values; 5]> {
+    let (grid, cell) = match most_constrained(&values) {
+        Some(guess) => guess,
+        None => return Some(values),
+    };
+    values[grid].0[cell].values().find_map(|d| {
+        let mut guess = values;
+        guess[grid] = guess[grid].assign(d, cell).ok()?;
+        search(synchronize(guess)?)
+    })
+}
+
+/// Counts the solutions, stopping once `limit` are found
+fn count_solutions(values: [Values; 5], limit: usize) -> usize {
+    let (grid, cell) = match most_constrained(&values) {
+        Some(guess) => guess,
+        None => return 1,
+    };
+    let mut count = 0;
+    for d in values[grid].0[cell].values() {
+        if count >= limit {
+            break;
+        }
+        let mut guess = values;
+        if let Ok(assigned) = guess[grid].assign(d, cell) {
+            guess[grid] = assigned;
+            if let Some(guess) = synchronize(guess) {
+                count += count_solutions(guess, limit - count);
+            }
+        }
+    }
+    count
+}
+
+impl FromStr for Samurai {
+    type Err = SudokuError;
+
+    /// Like a `Sudoku`, only digits and dots are read and everything
+    /// else is ignored, so there is no need to mark the gaps between
+    /// the grids.  The 369 cells are read row by row of the whole
+    /// puzzle, anything after them is ignored.  A shared cell is read
+    /// once, for both grids.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut digits = s
+            .chars()
+            .filter(|&c| c == '.' || c.is_ascii_digit())
+            .map(|c| c.to_digit(10).unwrap_or(0) as u8);
+        let mut grids = [[0; 81]; 5];
+        for spot in Samurai::index().iter().filter(|spot| !spot.is_empty()) {
+            let digit = digits.next().ok_or(SudokuError::TooFewCells)?;
+            for &(grid, cell) in spot {
+                grids[grid][cell] = digit;
+            }
+        }
+        Ok(Samurai {
+            grids: grids.map(|grid| Sudoku(grid, History::default())),
+        })
+    }
+}
+
+/// The 21x21 square, with blanks for the gaps
+impl fmt::Display for Samurai {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut buffer = String::new();
+        for (r, row) in Samurai::index().chunks(SIDE).enumerate() {
+            if r != 0 && r % 3 == 0 {
+                buffer.push('\n');
+            }
+            let mut line = String::new();
+            for (c, spot) in row.iter().enumerate() {
+                if c != 0 && c % 3 == 0 {
+                    line.push(' ');
+                }
+                match spot.first().map(|&(grid, cell)| self.grids[grid].0[cell]) {
+                    None => line.push_str("  "),
+                    Some(0) => line.push_str(". "),
+                    Some(d) => line.push_str(&(d.to_string() + " ")),
+                }
+            }
+            buffer.push_str(line.trim_end());
+            buffer.push('\n');
+        }
+        write!(f, "{}", buffer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Generated for these tests, and minimal: no clue can be removed
+    // without losing the unique solution
+    const PUZZLE: &str = "
+..67.....   ..4...7..
+.7..2.4..   .3...9.2.
+1.8..57..   ......3.5
+.9......2   .1...3...
+...9.1...   ......8..
+...4...8.   .96......
+..16...4.17....7.2..1
+.....8...............
+...57............849.
+      .....81..
+      ....5.7..
+      ..9....3.
+2...78...3....2..4.6.
+...1......1.....57..8
+.48..9..........8.23.
+..1..2.7.   .8.......
+...9.....   ......954
+..6.4...8   .9..6..1.
+........7   35..2...6
+59...1...   ....73.8.
+86..2...1   .........
+";
+
+    #[test]
+    fn test_samurai() {
+        let puzzle: Samurai = PUZZLE.parse().unwrap();
+        assert!(puzzle.is_unique());
+        // Only the shared boxes make it unique, no grid is on its own
+        assert!(puzzle.grids().iter().all(|grid| !grid.is_unique()));
+
+        let mut samurai = puzzle.clone();
+        assert!(samurai.solve());
+        let grids = samurai.grids();
+        assert!(grids.iter().all(Sudoku::is_solved));
+
+        // The middle grid shares a box with each of the others
+        for (corner, &(top, left)) in grids[..4].iter().zip(CORNERS.iter()) {
+            // Top left of the shared box in the 21x21 square
+            let (r, c) = (top.max(6), left.max(6));
+            for i in 0..9 {
+                let (r, c) = (r + i / 3, c + i % 3);
+                let here = (r - top) * 9 + c - left;
+                let in_middle = (r - 6) * 9 + c - 6;
+                assert_eq!(corner.0[here], grids[4].0[in_middle]);
+            }
+        }
+
+        // Clues are kept
+        for (solved, clues) in grids.iter().zip(puzzle.grids().iter()) {
+            assert!((0..81).all(|i| clues.0[i] == 0 || clues.0[i] == solved.0[i]));
+        }
+    }
+
+    #[test]
+    fn test_samurai_parse() {
+        assert_eq!(
+            PUZZLE[..200].parse::<Samurai>(),
+            Err(SudokuError::TooFewCells)
+        );
+
+        let puzzle: Samurai = PUZZLE.parse().unwrap();
+        let shown = puzzle.to_string();
+        assert_eq!(shown.parse::<Samurai>(), Ok(puzzle));
+        assert_eq!(
+            shown.lines().next(),
+            Some(". . 6  7 . .  . . .         . . 4  . . .  7 . .")
+        );
+    }
+}
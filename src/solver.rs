@@ -0,0 +1,296 @@
+//! A solver configured once and used on many puzzles, reusing the same
+//! memory
+
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::{
+    techniques, Guess, Heuristic, History, Possible, Search, SolveStats, Sudoku, Technique, Values,
+};
+
+/// How to solve puzzles, apart from the puzzles themselves: the
+/// heuristic of the search, the random numbers it may use, the
+/// techniques to try before guessing and how long to try.  It keeps
+/// the stack of guesses between puzzles, so a whole batch is solved
+/// without allocating anything after `Solver::new`.
+///
+/// A new solver solves like `Sudoku::solve`: constraint propagation and
+/// guessing on the most constrained cell, without a time limit.  The
+/// other `Sudoku::solve_*` methods are made of a solver too.
+#[derive(Debug)]
+pub struct Solver {
+    heuristic: Heuristic,
+    techniques: Vec<Technique>,
+    timeout: Option<Duration>,
+    rng: StdRng,
+    stack: Vec<Guess>,
+    steps: Vec<usize>,
+}
+
+/// What `Solver::solve` found out about a puzzle
+#[derive(Clone, Debug, PartialEq)]
+pub struct SolveResult {
+    /// Only when `status` is `SolveStatus::Solved`
+    pub solution: Option<Sudoku>,
+    pub stats: SolveStats,
+    pub status: SolveStatus,
+    /// How long `solve` took, from reading the grid to the solution
+    pub elapsed: Duration,
+}
+
+/// How `Solver::solve` ended
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SolveStatus {
+    Solved,
+    /// There is no solution
+    Unsolvable,
+    /// The techniques were not enough, and guessing is not allowed.
+    /// The puzzle may or may not have a solution.
+    Stuck,
+    /// The timeout was hit before finding a solution
+    TimedOut,
+}
+
+impl Default for Solver {
+    fn default() -> Self {
+        Solver::new()
+    }
+}
+
+impl Solver {
+    /// A solver with room for the deepest search possible, a guess on
+    /// every cell
+    pub fn new() -> Self {
+        Solver {
+            heuristic: Heuristic::MostConstrained,
+            techniques: vec![Technique::Guess],
+            timeout: None,
+            rng: StdRng::seed_from_u64(0),
+            stack: Vec::with_capacity(81),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Picks the cell to guess on with `heuristic`
+    pub fn with_heuristic(mut self, heuristic: Heuristic) -> Self {
+        self.heuristic = heuristic;
+        self
+    }
+
+    /// Tries `techniques` in order before guessing, and guesses only if
+    /// `Technique::Guess` is one of them, like
+    /// `Sudoku::solve_with_strategies`.  Singles are always used, they
+    /// come with the constraint propagation.
+    pub fn with_techniques(mut self, techniques: &[Technique]) -> Self {
+        self.techniques = techniques.to_vec();
+        self
+    }
+
+    /// Takes the random numbers of `Heuristic::Random` from `rng`, a
+    /// fixed seed otherwise.  They keep coming from it from one puzzle
+    /// to the next.
+    pub fn with_rng(mut self, rng: StdRng) -> Self {
+        self.rng = rng;
+        self
+    }
+
+    /// Gives up guessing after `timeout`, counted from the start of
+    /// every `solve`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Solves a copy of `sudoku`.  With the default configuration the
+    /// solution is the one `Sudoku::solve` finds, and so are the stats.
+    pub fn solve(&mut self, sudoku: &Sudoku) -> SolveResult {
+        let start = Instant::now();
+        let mut search = Search {
+            heuristic: self.heuristic,
+            deadline: self.timeout.map(|timeout| start + timeout),
+            rng: Some(self.rng.clone()),
+            ..Search::new()
+        };
+        let found = self.search(sudoku, &mut search).map(|values| {
+            values
+                .solved_grid()
+                .map(|grid| Sudoku(grid, History::default()))
+        });
+        let (solution, status) = match found {
+            // Don't trust the propagation, check the rules again
+            Ok(Some(solution)) if solution.is_solved() => (Some(solution), SolveStatus::Solved),
+            Ok(_) => (None, SolveStatus::Unsolvable),
+            Err(status) => (None, status),
+        };
+        // Carry on from where this search left the random numbers
+        if let Some(rng) = search.rng.take() {
+            self.rng = rng;
+        }
+        SolveResult {
+            solution,
+            stats: search.stats,
+            status,
+            elapsed: start.elapsed(),
+        }
+    }
+
+    /// Solves `sudoku` in place, like `solve`.  Returns whether there
+    /// was a solution, a puzzle without one is left untouched.
+    pub fn solve_into(&mut self, sudoku: &mut Sudoku) -> bool {
+        match self.solve(sudoku).solution {
+            Some(solution) => {
+                *sudoku = solution;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The techniques, then the search if allowed
+    fn search(&mut self, sudoku: &Sudoku, search: &mut Search) -> Result<Values, SolveStatus> {
+        let mut values = Values::from_grid(&sudoku.0).ok_or(SolveStatus::Unsolvable)?;
+        self.steps.clear();
+        if techniques::solve_logical(&mut values, &self.techniques, &mut self.steps) {
+            return Ok(values);
+        }
+        if values.0.iter().any(Possible::is_empty) {
+            return Err(SolveStatus::Unsolvable);
+        }
+        if !self.techniques.contains(&Technique::Guess) {
+            return Err(SolveStatus::Stuck);
+        }
+        match values.search_iterative(search, &mut self.stack) {
+            Some(values) => Ok(values),
+            None if search.aborted => Err(SolveStatus::TimedOut),
+            None => Err(SolveStatus::Unsolvable),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::convert::TryFrom;
+
+    const ESCARGOT: &str =
+        "1....7.9..3..2...8..96..5....53..9...1..8...26....4...3......1..4......7..7...3..";
+    const HARD: &str =
+        "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......";
+    // Generated for these tests, it needs an X-Wing
+    const XWING: &str =
+        "6...27.5.....853.4...3...1.83..7.1......4......12.......2.......8......7.7..6.8.9";
+
+    #[test]
+    fn test_solve_into() {
+        let mut solver = Solver::new();
+        let unsolvable = HARD.replacen('.', "4", 1);
+        for puzzle in &[ESCARGOT, HARD, &unsolvable, HARD] {
+            let mut sudoku = Sudoku::try_from(*puzzle).unwrap();
+            let mut expected = sudoku.clone();
+            let solved = expected.solve();
+            assert_eq!(solver.solve_into(&mut sudoku), solved);
+            assert_eq!(sudoku, expected);
+        }
+        // The stack never had to grow
+        assert_eq!(solver.stack.capacity(), 81);
+    }
+
+    #[test]
+    fn test_solve() {
+        let mut solver = Solver::new();
+        let puzzle = Sudoku::try_from(ESCARGOT).unwrap();
+        let mut expected = puzzle.clone();
+        let stats = expected.solve_stats();
+        let result = solver.solve(&puzzle);
+        assert_eq!(result.status, SolveStatus::Solved);
+        assert_eq!(result.solution, Some(expected));
+        assert_eq!(Some(result.stats), stats);
+
+        let unsolvable = Sudoku::try_from(HARD.replacen('.', "4", 1).as_str()).unwrap();
+        let result = solver.solve(&unsolvable);
+        assert_eq!(result.status, SolveStatus::Unsolvable);
+        assert_eq!(result.solution, None);
+
+        // The same search as `solve_with_heuristic`
+        let mut first_empty = Solver::new().with_heuristic(Heuristic::FirstEmpty);
+        let stats = puzzle.clone().solve_with_heuristic(Heuristic::FirstEmpty);
+        assert_eq!(Some(first_empty.solve(&puzzle).stats), stats);
+    }
+
+    #[test]
+    fn test_solve_techniques() {
+        let x_wing = Sudoku::try_from(XWING).unwrap();
+        let mut logical = Solver::new().with_techniques(&Technique::PIPELINE);
+        let result = logical.solve(&x_wing);
+        assert_eq!(result.status, SolveStatus::Solved);
+        assert_eq!(
+            result.solution,
+            x_wing.solve_with_strategies(&Technique::PIPELINE)
+        );
+        assert_eq!(result.stats.guesses, 0);
+
+        let result = logical.solve(&Sudoku::try_from(ESCARGOT).unwrap());
+        assert_eq!(result.status, SolveStatus::Stuck);
+        assert_eq!(result.solution, None);
+
+        let unsolvable = Sudoku::try_from(HARD.replacen('.', "4", 1).as_str()).unwrap();
+        assert_eq!(logical.solve(&unsolvable).status, SolveStatus::Unsolvable);
+
+        let mut singles = Solver::new().with_techniques(&[]);
+        assert_eq!(singles.solve(&x_wing).status, SolveStatus::Stuck);
+    }
+
+    #[test]
+    fn test_solve_random() {
+        let puzzle = Sudoku::try_from(HARD).unwrap();
+        let mut expected = puzzle.clone();
+        expected.solve();
+
+        let seeded = |seed| {
+            Solver::new()
+                .with_heuristic(Heuristic::Random)
+                .with_rng(StdRng::seed_from_u64(seed))
+        };
+        let mut guesses = Vec::new();
+        for seed in 0..5 {
+            let result = seeded(seed).solve(&puzzle);
+            assert_eq!(result.solution.as_ref(), Some(&expected));
+            // The same seed makes the same search
+            assert_eq!(seeded(seed).solve(&puzzle).stats, result.stats);
+            guesses.push(result.stats.guesses);
+        }
+        guesses.dedup();
+        assert!(guesses.len() > 1, "{:?}", guesses);
+    }
+
+    #[test]
+    fn test_solve_elapsed() {
+        let puzzle = Sudoku::try_from(HARD).unwrap();
+        let mut solver = Solver::new();
+        let t0 = Instant::now();
+        let result = solver.solve(&puzzle);
+        let total = t0.elapsed();
+        assert!(result.elapsed > Duration::from_secs(0));
+        assert!(result.elapsed <= total);
+    }
+
+    #[test]
+    fn test_solve_timeout() {
+        let mut solver = Solver::new().with_timeout(Duration::from_secs(0));
+        let result = solver.solve(&Sudoku::try_from(HARD).unwrap());
+        assert_eq!(result.status, SolveStatus::TimedOut);
+        assert_eq!(result.stats.guesses, 0);
+        assert!(result.elapsed < Duration::from_secs(1));
+        assert_eq!(result.solution, None);
+
+        let mut solver = Solver::new()
+            .with_timeout(Duration::from_secs(60))
+            .with_techniques(&[Technique::Guess]);
+        assert_eq!(
+            solver.solve(&Sudoku::try_from(HARD).unwrap()).status,
+            SolveStatus::Solved
+        );
+    }
+}
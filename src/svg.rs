@@ -0,0 +1,102 @@
+//! Drawing a grid as an SVG image, to print puzzle sheets
+
+use std::fmt::Write;
+
+use crate::Sudoku;
+
+/// Side of a cell, in pixels
+const CELL: usize = 40;
+
+/// Room around the grid for the outer border
+const MARGIN: usize = 2;
+
+impl Sudoku {
+    /// The grid as an SVG image, with thicker lines around the squares
+    /// and every digit in bold, as if they were all clues.
+    pub fn to_svg(&self) -> String {
+        self.to_svg_annotated(&self.givens())
+    }
+
+    /// Like `to_svg`, but only the digits in `givens` are in bold, so
+    /// a solution shows which cells were the clues of its puzzle.  Use
+    /// `puzzle.givens()` for the mask.
+    pub fn to_svg_annotated(&self, givens: &[bool; 81]) -> String {
+        let side = 9 * CELL + 2 * MARGIN;
+        let mut svg = String::new();
+        // Writing to a String never fails, so the results are ignored
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{0}" viewBox="0 0 {0} {0}">"#,
+            side
+        );
+        let _ = writeln!(
+            svg,
+            r#"<rect width="{0}" height="{0}" fill="white"/>"#,
+            side
+        );
+        for i in 0..=9 {
+            let at = MARGIN + i * CELL;
+            let (from, to) = (MARGIN, MARGIN + 9 * CELL);
+            let width = if i % 3 == 0 { 3 } else { 1 };
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{0}" y1="{1}" x2="{0}" y2="{2}" stroke="black" stroke-width="{3}"/>"#,
+                at, from, to, width
+            );
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{1}" y1="{0}" x2="{2}" y2="{0}" stroke="black" stroke-width="{3}"/>"#,
+                at, from, to, width
+            );
+        }
+        for (cell, (&digit, &given)) in self.0.iter().zip(givens.iter()).enumerate() {
+            if digit == 0 {
+                continue;
+            }
+            let x = MARGIN + cell % 9 * CELL + CELL / 2;
+            let y = MARGIN + cell / 9 * CELL + CELL / 2;
+            let weight = if given { "bold" } else { "normal" };
+            let _ = writeln!(
+                svg,
+                r#"<text x="{}" y="{}" font-family="sans-serif" font-size="{}" font-weight="{}" text-anchor="middle" dominant-baseline="central">{}</text>"#,
+                x,
+                y,
+                CELL * 2 / 3,
+                weight,
+                digit
+            );
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::convert::TryFrom;
+
+    const HARD: &str =
+        "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......";
+
+    #[test]
+    fn test_to_svg() {
+        let puzzle = Sudoku::try_from(HARD).unwrap();
+        let mut solution = puzzle.clone();
+        assert!(solution.solve());
+
+        let svg = solution.to_svg_annotated(&puzzle.givens());
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<text ").count(), 81);
+        assert_eq!(svg.matches(r#"font-weight="bold""#).count(), 17);
+        // 10 vertical and 10 horizontal lines, 4 of each thick
+        assert_eq!(svg.matches("<line ").count(), 20);
+        assert_eq!(svg.matches(r#"stroke-width="3""#).count(), 8);
+
+        let svg = puzzle.to_svg();
+        assert_eq!(svg.matches("<text ").count(), 17);
+        assert_eq!(svg.matches(r#"font-weight="bold""#).count(), 17);
+        assert_eq!(Sudoku::empty().to_svg().matches("<text ").count(), 0);
+    }
+}
@@ -0,0 +1,671 @@
+//! Human solving techniques, used to rate puzzles by how they would be
+//! solved by hand instead of by how many guesses the search needed.
+//!
+//! Every technique looks for one pattern in the candidates, removes
+//! whatever that pattern rules out and tells whether it found one.
+//! Removing a candidate goes through `Values::remove`, so singles are
+//! always propagated right away.
+//!
+//! Other techniques can be plugged in implementing `Strategy`, like the
+//! built-in ones do.
+
+use crate::{Possible, Sudoku, Values};
+
+/// A way to rule out candidates, to use with
+/// `Sudoku::solve_logical_with`.
+pub trait Strategy {
+    /// Looks for the pattern once, removing what it rules out with
+    /// `Values::remove`.  Returns whether anything was removed.
+    fn apply(&self, values: &mut Values) -> bool;
+
+    fn name(&self) -> &str;
+}
+
+impl<S: Strategy + ?Sized> Strategy for Box<S> {
+    fn apply(&self, values: &mut Values) -> bool {
+        (**self).apply(values)
+    }
+
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+}
+
+/// The techniques the logical solver knows, from the easiest to the
+/// hardest.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Technique {
+    /// Naked and hidden singles, done by the constraint propagation
+    Singles,
+    /// A digit confined to a line within a box, or to a box within a
+    /// line
+    LockedCandidates,
+    NakedPair,
+    HiddenPair,
+    NakedTriple,
+    HiddenTriple,
+    XWing,
+    XYWing,
+    Swordfish,
+    /// Only valid for puzzles with a unique solution
+    UniqueRectangle,
+    /// Not a technique: the logical solver got stuck and the search
+    /// had to guess
+    Guess,
+}
+
+impl Technique {
+    /// The techniques the logical solver tries, in order.  It leaves
+    /// out `UniqueRectangle`, which is only sound on a puzzle with a
+    /// single solution.
+    pub const PIPELINE: [Technique; 8] = [
+        Technique::LockedCandidates,
+        Technique::NakedPair,
+        Technique::HiddenPair,
+        Technique::NakedTriple,
+        Technique::HiddenTriple,
+        Technique::XWing,
+        Technique::XYWing,
+        Technique::Swordfish,
+    ];
+
+    /// `PIPELINE` and then the techniques that assume the puzzle has a
+    /// single solution
+    pub(crate) const UNIQUE_PIPELINE: [Technique; 9] = [
+        Technique::LockedCandidates,
+        Technique::NakedPair,
+        Technique::HiddenPair,
+        Technique::NakedTriple,
+        Technique::HiddenTriple,
+        Technique::XWing,
+        Technique::XYWing,
+        Technique::Swordfish,
+        Technique::UniqueRectangle,
+    ];
+
+    /// How much every use of the technique adds to `Sudoku::score`,
+    /// roughly how many times harder than a single it is to spot:
+    ///
+    /// | technique         | weight |
+    /// |-------------------|--------|
+    /// | singles           | 1      |
+    /// | locked candidates | 3      |
+    /// | naked pair        | 5      |
+    /// | hidden pair       | 8      |
+    /// | naked triple      | 10     |
+    /// | hidden triple     | 12     |
+    /// | X-Wing            | 15     |
+    /// | XY-Wing           | 20     |
+    /// | Swordfish         | 25     |
+    /// | unique rectangle  | 30     |
+    /// | guess             | 100    |
+    pub fn weight(self) -> u32 {
+        match self {
+            Technique::Singles => 1,
+            Technique::LockedCandidates => 3,
+            Technique::NakedPair => 5,
+            Technique::HiddenPair => 8,
+            Technique::NakedTriple => 10,
+            Technique::HiddenTriple => 12,
+            Technique::XWing => 15,
+            Technique::XYWing => 20,
+            Technique::Swordfish => 25,
+            Technique::UniqueRectangle => 30,
+            Technique::Guess => 100,
+        }
+    }
+
+    /// The built-in techniques, ready to add more to them and use
+    /// them with `Sudoku::solve_logical_with`.  The unique rectangle is
+    /// left out, add it for puzzles known to have a single solution.
+    pub fn pipeline() -> Vec<Box<dyn Strategy>> {
+        Technique::PIPELINE
+            .iter()
+            .map(|&t| Box::new(t) as Box<dyn Strategy>)
+            .collect()
+    }
+}
+
+impl Strategy for Technique {
+    fn apply(&self, values: &mut Values) -> bool {
+        match self {
+            Technique::LockedCandidates => locked_candidates(values),
+            Technique::NakedPair => naked_pairs(values),
+            Technique::HiddenPair => hidden_pairs(values),
+            Technique::NakedTriple => naked_triples(values),
+            Technique::HiddenTriple => hidden_triples(values),
+            Technique::XWing => x_wing(values),
+            Technique::XYWing => xy_wing(values),
+            Technique::Swordfish => swordfish(values),
+            Technique::UniqueRectangle => unique_rectangle(values),
+            // Nothing to look for, these happen on their own
+            Technique::Singles | Technique::Guess => false,
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            Technique::Singles => "singles",
+            Technique::LockedCandidates => "locked candidates",
+            Technique::NakedPair => "naked pair",
+            Technique::HiddenPair => "hidden pair",
+            Technique::NakedTriple => "naked triple",
+            Technique::HiddenTriple => "hidden triple",
+            Technique::XWing => "X-Wing",
+            Technique::XYWing => "XY-Wing",
+            Technique::Swordfish => "Swordfish",
+            Technique::UniqueRectangle => "unique rectangle",
+            Technique::Guess => "guess",
+        }
+    }
+}
+
+/// Applies the strategies in order, starting over from the first one
+/// after every success, until the grid is solved or none of them
+/// works.  The index of every one that succeeds is pushed to `steps`.
+/// Returns whether the grid was solved.
+pub(crate) fn solve_logical<S: Strategy>(
+    values: &mut Values,
+    strategies: &[S],
+    steps: &mut Vec<usize>,
+) -> bool {
+    loop {
+        if values.0.iter().any(Possible::is_empty) {
+            return false;
+        }
+        if values.0.iter().all(|p| p.len() == 1) {
+            return true;
+        }
+        match strategies.iter().position(|s| s.apply(values)) {
+            Some(i) => steps.push(i),
+            None => return false,
+        }
+    }
+}
+
+/// The cells of the 9 rows, then the 9 columns, then the 9 boxes
+fn units() -> impl Iterator<Item = [usize; 9]> {
+    (0..27).map(|u| {
+        let cells = match u / 9 {
+            0 => Sudoku::row_cells(u % 9),
+            1 => Sudoku::column_cells(u % 9),
+            _ => Sudoku::box_cells(u % 9),
+        };
+        cells.map(usize::from)
+    })
+}
+
+fn box_of(cell: usize) -> usize {
+    cell / 27 * 3 + cell % 9 / 3
+}
+
+/// Whether two different cells share a row, a column or a box
+fn sees(a: usize, b: usize) -> bool {
+    a != b && (a / 9 == b / 9 || a % 9 == b % 9 || box_of(a) == box_of(b))
+}
+
+/// Removes `digit` from every cell of `cells` but the ones in `keep`
+fn remove_from(values: &mut Values, digit: u8, cells: &[usize], keep: &[usize]) -> bool {
+    let mut removed = false;
+    for &cell in cells.iter().filter(|c| !keep.contains(c)) {
+        removed |= values.remove(digit, cell);
+    }
+    removed
+}
+
+/// The cells of `unit` where `digit` can go
+fn places(values: &Values, unit: &[usize; 9], digit: u8) -> Vec<usize> {
+    unit.iter()
+        .copied()
+        .filter(|&c| values.0[c].contains(digit))
+        .collect()
+}
+
+/// If a digit can only go in one row (or column) of a box, it can't go
+/// in that row anywhere else.  And the other way around: if within a
+/// row it can only go in one box, it can't go anywhere else in that
+/// box.
+pub(crate) fn locked_candidates(values: &mut Values) -> bool {
+    let units: Vec<_> = units().collect();
+    for digit in 1..=9 {
+        for (u, unit) in units.iter().enumerate() {
+            let places = places(values, unit, digit);
+            if places.len() < 2 {
+                continue;
+            }
+            let targets: Vec<usize> = if u >= 18 {
+                // A box, pointing to a line
+                if places.iter().all(|&c| c / 9 == places[0] / 9) {
+                    vec![places[0] / 9]
+                } else if places.iter().all(|&c| c % 9 == places[0] % 9) {
+                    vec![9 + places[0] % 9]
+                } else {
+                    vec![]
+                }
+            } else if places.iter().all(|&c| box_of(c) == box_of(places[0])) {
+                // A line, claiming a box
+                vec![18 + box_of(places[0])]
+            } else {
+                vec![]
+            };
+            for target in targets {
+                if remove_from(values, digit, &units[target], &places) {
+                    return true;
+                }
+            }
+        }
+    }
+    false
+}
+
+/// Two cells of a unit with the same two candidates get those two
+/// digits, so no other cell of the unit can have them.
+pub(crate) fn naked_pairs(values: &mut Values) -> bool {
+    naked_subsets(values, 2)
+}
+
+/// Like `naked_pairs`, with three cells that only have three digits
+/// between them, not necessarily all three in every cell.
+pub(crate) fn naked_triples(values: &mut Values) -> bool {
+    naked_subsets(values, 3)
+}
+
+/// Two digits that can only go in the same two cells of a unit take
+/// those cells, so they can't hold anything else.
+pub(crate) fn hidden_pairs(values: &mut Values) -> bool {
+    hidden_subsets(values, 2)
+}
+
+/// Like `hidden_pairs`, with three digits that can only go in three
+/// cells between them.
+pub(crate) fn hidden_triples(values: &mut Values) -> bool {
+    hidden_subsets(values, 3)
+}
+
+/// `k` undecided cells of a unit with only `k` candidates between them
+fn naked_subsets(values: &mut Values, k: usize) -> bool {
+    for unit in units() {
+        let open: Vec<usize> = unit
+            .iter()
+            .copied()
+            .filter(|&c| (2..=k as u32).contains(&values.0[c].len()))
+            .collect();
+        for pick in combinations(open.len(), k) {
+            let cells: Vec<usize> = pick.iter().map(|&i| open[i]).collect();
+            let digits = Possible(cells.iter().fold(0, |bits, &c| bits | values.0[c].0));
+            if digits.len() != k as u32 {
+                continue;
+            }
+            let mut removed = false;
+            for digit in digits.values() {
+                removed |= remove_from(values, digit, &unit, &cells);
+            }
+            if removed {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// `k` digits that can only go in `k` cells of a unit between them
+fn hidden_subsets(values: &mut Values, k: usize) -> bool {
+    for unit in units() {
+        let open: Vec<u8> = (1..=9)
+            .filter(|&d| (2..=k).contains(&places(values, &unit, d).len()))
+            .collect();
+        for pick in combinations(open.len(), k) {
+            let digits: Vec<u8> = pick.iter().map(|&i| open[i]).collect();
+            let mut cells: Vec<usize> = digits
+                .iter()
+                .flat_map(|&d| places(values, &unit, d))
+                .collect();
+            cells.sort_unstable();
+            cells.dedup();
+            if cells.len() != k {
+                continue;
+            }
+            let mut removed = false;
+            for &cell in cells.iter() {
+                for other in values.0[cell].values().filter(|d| !digits.contains(d)) {
+                    removed |= values.remove(other, cell);
+                }
+            }
+            if removed {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Every way to pick `k` of the numbers below `n`, each one sorted
+fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+    if k == 0 {
+        return vec![vec![]];
+    }
+    (k - 1..n)
+        .flat_map(|last| {
+            combinations(last, k - 1).into_iter().map(move |mut pick| {
+                pick.push(last);
+                pick
+            })
+        })
+        .collect()
+}
+
+/// If in two rows a digit can only go in the same two columns, it goes
+/// in those columns in those rows, so it can't go anywhere else in the
+/// columns.  The same with rows and columns swapped.
+pub(crate) fn x_wing(values: &mut Values) -> bool {
+    fish(values, 2)
+}
+
+/// Like `x_wing`, with three rows where a digit can only go in three
+/// columns between them, not necessarily all three in every row.
+pub(crate) fn swordfish(values: &mut Values) -> bool {
+    fish(values, 3)
+}
+
+/// `size` rows (or columns) where a digit can only go in `size`
+/// columns (or rows) between them
+fn fish(values: &mut Values, size: usize) -> bool {
+    let units: Vec<_> = units().collect();
+    for digit in 1..=9 {
+        // Rows with columns, then columns with rows
+        for &(lines, crossing) in [(0, 9), (9, 0)].iter() {
+            let positions: Vec<Vec<usize>> = units[lines..lines + 9]
+                .iter()
+                .map(|line| {
+                    (0..9)
+                        .filter(|&j| values.0[line[j]].contains(digit))
+                        .collect()
+                })
+                .collect();
+            let open: Vec<usize> = (0..9)
+                .filter(|&l| (2..=size).contains(&positions[l].len()))
+                .collect();
+            for pick in combinations(open.len(), size) {
+                let picked: Vec<usize> = pick.iter().map(|&i| open[i]).collect();
+                let mut crossings: Vec<usize> = picked
+                    .iter()
+                    .flat_map(|&l| positions[l].iter().copied())
+                    .collect();
+                crossings.sort_unstable();
+                crossings.dedup();
+                if crossings.len() != size {
+                    continue;
+                }
+                let keep: Vec<usize> = picked
+                    .iter()
+                    .flat_map(|&l| crossings.iter().map(move |&j| (l, j)))
+                    .map(|(l, j)| units[lines + l][j])
+                    .collect();
+                let mut removed = false;
+                for &j in crossings.iter() {
+                    removed |= remove_from(values, digit, &units[crossing + j], &keep);
+                }
+                if removed {
+                    return true;
+                }
+            }
+        }
+    }
+    false
+}
+
+/// A pivot cell with candidates {X,Y} that sees a cell with {X,Z} and
+/// another one with {Y,Z}: whatever the pivot gets, one of the two
+/// pincers is Z, so Z can't go in any cell that sees both.
+pub(crate) fn xy_wing(values: &mut Values) -> bool {
+    let pairs: Vec<usize> = (0..81).filter(|&c| values.0[c].len() == 2).collect();
+    for &pivot in pairs.iter() {
+        let xy = values.0[pivot];
+        for &a in pairs.iter().filter(|&&a| sees(a, pivot)) {
+            let x = Possible(values.0[a].0 & xy.0);
+            if x.len() != 1 {
+                continue;
+            }
+            let y = xy.remove(x.n()).n();
+            let z = values.0[a].remove(x.n()).n();
+            let yz = Possible(1 << (y - 1) | 1 << (z - 1));
+            let pincers: Vec<usize> = pairs
+                .iter()
+                .copied()
+                .filter(|&b| values.0[b] == yz && sees(b, pivot))
+                .collect();
+            for b in pincers {
+                let mut removed = false;
+                for cell in (0..81).filter(|&c| sees(c, a) && sees(c, b)) {
+                    removed |= values.remove(z, cell);
+                }
+                if removed {
+                    return true;
+                }
+            }
+        }
+    }
+    false
+}
+
+/// Four cells in two rows, two columns and two boxes can't all have
+/// the same two candidates {X,Y}: their digits could be swapped, giving
+/// two solutions.  So if three of them only have {X,Y}, the fourth one
+/// is neither X nor Y.
+///
+/// This assumes the puzzle has a unique solution, and may remove the
+/// right digits from a puzzle with more than one.
+pub(crate) fn unique_rectangle(values: &mut Values) -> bool {
+    for r1 in 0..9 {
+        for r2 in r1 + 1..9 {
+            for c1 in 0..9 {
+                for c2 in c1 + 1..9 {
+                    // Either the rows share a band or the columns a
+                    // stack, but not both
+                    if (r1 / 3 == r2 / 3) == (c1 / 3 == c2 / 3) {
+                        continue;
+                    }
+                    let corners = [r1 * 9 + c1, r1 * 9 + c2, r2 * 9 + c1, r2 * 9 + c2];
+                    for &extra in corners.iter() {
+                        let mut others = corners.iter().filter(|&&c| c != extra);
+                        let xy = values.0[*others.next().unwrap()];
+                        let pattern = xy.len() == 2
+                            && others.all(|&c| values.0[c] == xy)
+                            && values.0[extra].len() > 2
+                            && values.0[extra].0 & xy.0 == xy.0;
+                        if pattern {
+                            for digit in xy.values() {
+                                values.remove(digit, extra);
+                            }
+                            return true;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn without(values: &mut Values, digit: u8, cells: impl Iterator<Item = usize>) {
+        for cell in cells {
+            values.0[cell] = values.0[cell].remove(digit);
+        }
+    }
+
+    #[test]
+    fn test_units() {
+        let units: Vec<_> = units().collect();
+        assert_eq!(units[1], [9, 10, 11, 12, 13, 14, 15, 16, 17]);
+        assert_eq!(units[10], [1, 10, 19, 28, 37, 46, 55, 64, 73]);
+        assert_eq!(units[22], [30, 31, 32, 39, 40, 41, 48, 49, 50]);
+        assert!(units[18..]
+            .iter()
+            .enumerate()
+            .all(|(b, u)| u.iter().all(|&c| box_of(c) == b)));
+    }
+
+    #[test]
+    fn test_locked_candidates() {
+        // In the first box 5 can only go in the first row
+        let mut values = Values::new();
+        without(&mut values, 5, 9..12);
+        without(&mut values, 5, 18..21);
+
+        assert!(locked_candidates(&mut values));
+        assert!((3..9).all(|c| !values.0[c].contains(5)));
+        assert!((0..3).all(|c| values.0[c].contains(5)));
+        assert!(values.0[27].contains(5));
+    }
+
+    #[test]
+    fn test_naked_pairs() {
+        let mut values = Values::new();
+        values.0[0] = Possible(0b11);
+        values.0[1] = Possible(0b11);
+
+        assert!(naked_pairs(&mut values));
+        // The first row and the first box have the pair, the first
+        // unit found is the row
+        assert!((2..9).all(|c| !values.0[c].contains(1) && !values.0[c].contains(2)));
+        assert_eq!(values.0[0], Possible(0b11));
+        assert!(values.0[9].contains(1));
+
+        assert!(naked_pairs(&mut values));
+        assert!(!values.0[9].contains(1));
+        assert!(!naked_pairs(&mut values));
+    }
+
+    #[test]
+    fn test_hidden_pairs() {
+        let mut values = Values::new();
+        without(&mut values, 1, 2..9);
+        without(&mut values, 2, 2..9);
+
+        assert!(!naked_pairs(&mut values));
+        assert!(hidden_pairs(&mut values));
+        assert_eq!(values.0[0], Possible(0b11));
+        assert_eq!(values.0[1], Possible(0b11));
+    }
+
+    #[test]
+    fn test_combinations() {
+        assert_eq!(combinations(3, 2), vec![vec![0, 1], vec![0, 2], vec![1, 2]]);
+        assert_eq!(combinations(9, 3).len(), 84);
+        assert!(combinations(2, 3).is_empty());
+    }
+
+    #[test]
+    fn test_naked_triples() {
+        // {1,2}, {2,3} and {1,3}: no two cells make a pair
+        let mut values = Values::new();
+        values.0[0] = Possible(0b011);
+        values.0[1] = Possible(0b110);
+        values.0[2] = Possible(0b101);
+
+        assert!(!naked_pairs(&mut values));
+        assert!(!hidden_pairs(&mut values));
+        assert!(naked_triples(&mut values));
+        assert!((3..9).all(|c| (1..=3).all(|d| !values.0[c].contains(d))));
+        assert!(values.0[9].contains(1));
+    }
+
+    #[test]
+    fn test_hidden_triples() {
+        // 1, 2 and 3 can only go in the first three cells of the row
+        let mut values = Values::new();
+        for digit in 1..=3 {
+            without(&mut values, digit, 3..9);
+        }
+
+        assert!(!naked_pairs(&mut values));
+        assert!(!hidden_pairs(&mut values));
+        assert!(!naked_triples(&mut values));
+        assert!(hidden_triples(&mut values));
+        assert!((0..3).all(|c| values.0[c] == Possible(0b111)));
+    }
+
+    #[test]
+    fn test_x_wing() {
+        // 7 can only go in columns 2 and 6 in rows 1 and 5
+        let mut values = Values::new();
+        let elsewhere = |row: usize| {
+            (0..9)
+                .filter(|&j| j != 2 && j != 6)
+                .map(move |j| row * 9 + j)
+        };
+        without(&mut values, 7, elsewhere(1));
+        without(&mut values, 7, elsewhere(5));
+
+        assert!(x_wing(&mut values));
+        for row in 0..9 {
+            let kept = row == 1 || row == 5;
+            assert_eq!(values.0[row * 9 + 2].contains(7), kept);
+            assert_eq!(values.0[row * 9 + 6].contains(7), kept);
+        }
+        assert!(values.0[9 * 3].contains(7));
+    }
+
+    #[test]
+    fn test_swordfish() {
+        // 4 can only go in columns 1 and 5 in row 0, 5 and 7 in row 4,
+        // and 1 and 7 in row 8
+        let mut values = Values::new();
+        let only = [(0, [1, 5]), (4, [5, 7]), (8, [1, 7])];
+        for &(row, columns) in only.iter() {
+            let elsewhere = (0..9).filter(|j| !columns.contains(j));
+            without(&mut values, 4, elsewhere.map(|j| row * 9 + j));
+        }
+
+        assert!(!x_wing(&mut values));
+        assert!(swordfish(&mut values));
+        for row in 0..9 {
+            for &column in [1, 5, 7].iter() {
+                let kept = only.iter().any(|&(r, c)| r == row && c.contains(&column));
+                assert_eq!(values.0[row * 9 + column].contains(4), kept);
+            }
+        }
+        assert!(values.0[9 * 3].contains(4));
+    }
+
+    #[test]
+    fn test_xy_wing() {
+        // Pivot {1,2} in r1c1, pincers {1,3} in r1c5 and {2,3} in r4c1
+        let mut values = Values::new();
+        values.0[0] = Possible(0b011);
+        values.0[4] = Possible(0b101);
+        values.0[27] = Possible(0b110);
+
+        assert!(xy_wing(&mut values));
+        // r4c5 is the only cell seeing both pincers
+        assert!(!values.0[31].contains(3));
+        assert_eq!(values.0.iter().filter(|p| p.len() == 9).count(), 81 - 4);
+        assert!(!xy_wing(&mut values));
+    }
+
+    #[test]
+    fn test_unique_rectangle() {
+        // {1,2} in r1c1, r1c5 and r2c1, and {1,2,5} in r2c5
+        let mut values = Values::new();
+        for &cell in [0, 4, 9].iter() {
+            values.0[cell] = Possible(0b11);
+        }
+        values.0[13] = Possible(0b10011);
+
+        assert!(unique_rectangle(&mut values));
+        assert_eq!(values.0[13], Possible(0b10000));
+        assert!(!unique_rectangle(&mut values));
+
+        // Four boxes, the digits can't be swapped
+        let mut values = Values::new();
+        for &cell in [0, 4, 36].iter() {
+            values.0[cell] = Possible(0b11);
+        }
+        values.0[40] = Possible(0b10011);
+        assert!(!unique_rectangle(&mut values));
+    }
+}
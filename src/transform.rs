@@ -0,0 +1,267 @@
+//! Transformations of a puzzle.  The symmetries of the grid keep how
+//! it solves: the result has exactly as many solutions as the
+//! original, through the same transformation.  The others change the
+//! clues on purpose, and `detected_symmetry` tells the pattern they
+//! make.
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::generator::{remove_clues, MAX_ATTEMPTS, MIN_CLUES};
+use crate::{GenerateOptions, History, Sudoku, Symmetry};
+
+impl Sudoku {
+    /// A random puzzle equivalent to this one: the bands, the stacks,
+    /// the rows in every band and the columns in every stack are
+    /// shuffled, the digits relabelled and half of the time the grid
+    /// is transposed.
+    pub fn random_equivalent<R: Rng + ?Sized>(&self, rng: &mut R) -> Sudoku {
+        let rows = shuffled_lines(rng);
+        let columns = shuffled_lines(rng);
+        let mut digits: Vec<u8> = (1..=9).collect();
+        digits.shuffle(rng);
+
+        let mut grid = [0u8; 81];
+        for (cell, digit) in grid.iter_mut().enumerate() {
+            let original = self.0[rows[cell / 9] * 9 + columns[cell % 9]];
+            if original != 0 {
+                *digit = digits[original as usize - 1];
+            }
+        }
+        let sudoku = Sudoku(grid, History::default());
+        if rng.gen() {
+            sudoku.transpose()
+        } else {
+            sudoku
+        }
+    }
+
+    /// The puzzle mirrored along its main diagonal: rows become
+    /// columns.
+    pub fn transpose(&self) -> Sudoku {
+        let mut grid = [0u8; 81];
+        for (cell, digit) in grid.iter_mut().enumerate() {
+            *digit = self.0[cell % 9 * 9 + cell / 9];
+        }
+        Sudoku(grid, History::default())
+    }
+
+    /// The puzzle with clues added, from one of its solutions, until
+    /// it has only that one.  Every clue added is a cell where that
+    /// solution and another one differ, picked at random, so none is
+    /// wasted on cells that were already decided.  A puzzle without
+    /// solution, or with a single one, is returned as it is.
+    pub fn complete_to_unique<R: Rng + ?Sized>(&self, rng: &mut R) -> Sudoku {
+        let mut puzzle = self.clone();
+        let mut solution = self.clone();
+        if !solution.solve() {
+            return puzzle;
+        }
+
+        let mut solutions = Vec::with_capacity(2);
+        loop {
+            solutions.clear();
+            puzzle.solve_all_into(&mut solutions, 2);
+            let other = match solutions.iter().find(|&grid| grid != &solution.0) {
+                Some(other) => Sudoku(*other, History::default()),
+                None => return puzzle,
+            };
+            let differences = solution.diff(&other);
+            // We can unwrap safely because two different solutions
+            // differ somewhere
+            let &(cell, digit, _) = differences.choose(rng).unwrap();
+            puzzle.0[cell] = digit;
+        }
+    }
+
+    /// Another puzzle with at most `target_clues` clues and the same
+    /// single solution, taken from it at random.  Several tries are
+    /// made, `None` if none got down to `target_clues` with other clues
+    /// than these while keeping the solution unique, or if this puzzle
+    /// does not have a unique solution to keep.
+    pub fn reshuffle_clues<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        target_clues: usize,
+    ) -> Option<Sudoku> {
+        if target_clues < MIN_CLUES || !self.is_unique() {
+            return None;
+        }
+        let mut solution = self.clone();
+        solution.solve();
+
+        let options = GenerateOptions {
+            minimal: false,
+            max_clues: Some(target_clues),
+            ..GenerateOptions::default()
+        };
+        (0..MAX_ATTEMPTS)
+            .map(|_| remove_clues(solution.clone(), rng, &options))
+            .find(|puzzle| puzzle.clue_count() <= target_clues && puzzle.givens() != self.givens())
+    }
+
+    /// The symmetry the clues follow, ignoring their digits.  If they
+    /// follow more than one the first in the order of `Symmetry` is
+    /// returned, so a rotational pattern is always reported as such,
+    /// even if it is also mirrored both ways.  `Symmetry::None` if
+    /// they follow none.
+    pub fn detected_symmetry(&self) -> Symmetry {
+        let givens = self.givens();
+        Symmetry::ALL
+            .iter()
+            .copied()
+            .find(|symmetry| (0..81).all(|cell| givens[cell] == givens[symmetry.mirror(cell)]))
+            .unwrap_or(Symmetry::None)
+    }
+}
+
+/// The nine rows, or columns, in a random order that keeps the ones
+/// in a band together
+fn shuffled_lines<R: Rng + ?Sized>(rng: &mut R) -> [usize; 9] {
+    let mut bands = [0, 1, 2];
+    bands.shuffle(rng);
+    let mut lines = [0; 9];
+    for (band, chunk) in bands.iter().zip(lines.chunks_mut(3)) {
+        let mut within = [0, 1, 2];
+        within.shuffle(rng);
+        for (line, offset) in chunk.iter_mut().zip(within.iter()) {
+            *line = band * 3 + offset;
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::generate;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::convert::TryFrom;
+
+    const HARD: &str =
+        "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......";
+
+    #[test]
+    fn test_random_equivalent() {
+        let puzzle = Sudoku::try_from(HARD).unwrap();
+        let mut solution = puzzle.clone();
+        assert!(solution.solve());
+
+        for seed in 0..20 {
+            let equivalent = puzzle.random_equivalent(&mut StdRng::seed_from_u64(seed));
+            assert_eq!(equivalent.clue_count(), puzzle.clue_count());
+            assert!(equivalent.is_unique());
+
+            // The same transformation takes the solution to the solution
+            let mut solved = equivalent.clone();
+            assert!(solved.solve());
+            assert_eq!(
+                solved,
+                solution.random_equivalent(&mut StdRng::seed_from_u64(seed))
+            );
+        }
+
+        let mut ambiguous = puzzle;
+        ambiguous.0[0] = 0;
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..5 {
+            assert!(!ambiguous.random_equivalent(&mut rng).is_unique());
+        }
+    }
+
+    #[test]
+    fn test_transpose() {
+        let sudoku = Sudoku::try_from(HARD).unwrap();
+        let transposed = sudoku.transpose();
+        assert_ne!(transposed, sudoku);
+        assert_eq!(transposed.transpose(), sudoku);
+        for (r, row) in transposed.to_rows().iter().enumerate() {
+            for (c, &digit) in row.iter().enumerate() {
+                assert_eq!(digit, sudoku.to_rows()[c][r]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_complete_to_unique() {
+        let mut rng = StdRng::seed_from_u64(9);
+        let (puzzle, _) = generate(&mut rng, GenerateOptions::default()).unwrap();
+        let mut partial = puzzle.clone();
+        for cell in (0..81).filter(|&c| puzzle.0[c] != 0).take(6) {
+            partial.0[cell] = 0;
+        }
+        assert!(!partial.is_unique());
+
+        for _ in 0..5 {
+            let completed = partial.complete_to_unique(&mut rng);
+            assert!(completed.is_unique());
+            // Only clues added, none changed
+            assert!(partial
+                .diff(&completed)
+                .iter()
+                .all(|&(_, before, _)| before == 0));
+            assert!(completed.clue_count() > partial.clue_count());
+        }
+
+        assert_eq!(puzzle.complete_to_unique(&mut rng), puzzle);
+        let unsolvable = Sudoku::try_from(
+            "44....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......",
+        )
+        .unwrap();
+        assert_eq!(unsolvable.complete_to_unique(&mut rng), unsolvable);
+    }
+
+    #[test]
+    fn test_reshuffle_clues() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let (puzzle, _) = generate(&mut rng, GenerateOptions::default()).unwrap();
+        let mut solution = puzzle.clone();
+        solution.solve();
+
+        let target = puzzle.clue_count() + 5;
+        let reshuffled = puzzle.reshuffle_clues(&mut rng, target).unwrap();
+        assert_ne!(reshuffled.givens(), puzzle.givens());
+        assert!(reshuffled.clue_count() <= target);
+        assert!(reshuffled.is_unique());
+        // Its clues come from the same solution
+        assert!(reshuffled
+            .diff(&solution)
+            .iter()
+            .all(|&(_, clue, _)| clue == 0));
+
+        assert_eq!(puzzle.reshuffle_clues(&mut rng, 16), None);
+        let mut ambiguous = puzzle.clone();
+        let cell = (0..81).find(|&c| puzzle.0[c] != 0).unwrap();
+        ambiguous.0[cell] = 0;
+        assert_eq!(ambiguous.reshuffle_clues(&mut rng, 40), None);
+    }
+
+    #[test]
+    fn test_detected_symmetry() {
+        let mut solution = Sudoku::try_from(HARD).unwrap();
+        assert_eq!(solution.detected_symmetry(), Symmetry::None);
+        assert!(solution.solve());
+        assert_eq!(solution.detected_symmetry(), Symmetry::Rotational);
+
+        for &symmetry in Symmetry::ALL.iter() {
+            let mut puzzle = Sudoku::empty();
+            for &cell in &[1, 2, 12] {
+                for &clue in &[cell, symmetry.mirror(cell)] {
+                    puzzle.0[clue] = solution.0[clue];
+                }
+            }
+            assert_eq!(puzzle.detected_symmetry(), symmetry);
+            // A single clue more breaks it
+            puzzle.0[3] = solution.0[3];
+            assert_eq!(puzzle.detected_symmetry(), Symmetry::None);
+        }
+
+        // Both mirrors at once are a rotation too
+        let mut both = Sudoku::empty();
+        for &cell in &[0, 8, 72, 80] {
+            both.0[cell] = solution.0[cell];
+        }
+        assert_eq!(both.detected_symmetry(), Symmetry::Rotational);
+    }
+}
@@ -0,0 +1,206 @@
+//! Runs the binary on a few puzzles and checks what it prints
+
+use std::convert::TryFrom;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use sudoku_solver::{Difficulty, Sudoku};
+
+const HARD: &str =
+    "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......";
+
+/// Runs the binary with `args`, feeding it `input`, and returns stdout
+/// after checking it succeeded
+fn run(args: &[&str], input: &str) -> String {
+    run_with_status(args, input, 0)
+}
+
+/// Same as `run`, but checks the binary exits with `status` instead
+fn run_with_status(args: &[&str], input: &str, status: i32) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sudoku_solver"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("could not run the binary");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(status));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_json() {
+    let unsolvable = HARD.replacen('.', "4", 1);
+    let output = run_with_status(&["--json"], &format!("{}\n\n{}\n\n", HARD, unsolvable), 1);
+    let lines: Vec<serde_json::Value> = output
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+
+    assert_eq!(lines[0]["puzzle"], HARD);
+    let solution = lines[0]["solution"].as_str().unwrap();
+    assert!(solution.starts_with("417369825"));
+    assert_eq!(lines[0]["solvable"], true);
+    assert_eq!(lines[0]["unique"], true);
+    assert!(lines[0]["micros"].is_u64());
+
+    assert_eq!(lines[1]["puzzle"], unsolvable.as_str());
+    assert!(lines[1]["solution"].is_null());
+    assert_eq!(lines[1]["solvable"], false);
+    assert_eq!(lines[1]["unique"], false);
+}
+
+#[test]
+fn test_csv() {
+    let unsolvable = HARD.replacen('.', "4", 1);
+    let output = run_with_status(&["--csv"], &format!("{}\n\n{}\n\n", HARD, unsolvable), 1);
+    let rows: Vec<Vec<&str>> = output.lines().map(|l| l.split(',').collect()).collect();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(
+        rows[0],
+        ["puzzle", "solution", "solvable", "unique", "clues", "micros"]
+    );
+    assert!(rows.iter().all(|row| row.len() == 6));
+
+    assert_eq!(rows[1][0], HARD);
+    assert!(rows[1][1].starts_with("417369825"));
+    assert_eq!(rows[1][2..5], ["true", "true", "17"]);
+    assert!(rows[1][5].parse::<u64>().is_ok());
+    assert_eq!(rows[2][1..5], ["", "false", "false", "18"]);
+}
+
+#[test]
+fn test_stats() {
+    const ESCARGOT: &str =
+        "1....7.9..3..2...8..96..5....53..9...1..8...26....4...3......1..4......7..7...3..";
+    let unsolvable = HARD.replacen('.', "4", 1);
+    let input = format!("{}\n\n{}\n\n{}\n\n", HARD, ESCARGOT, unsolvable);
+    let output = run_with_status(&["--stats", "--json"], &input, 1);
+    let summary: Vec<&str> = output.lines().skip(3).collect();
+    assert_eq!(
+        summary,
+        [
+            "puzzles                3",
+            "logic only             1",
+            "guessing               1",
+            "unsolvable             1",
+            "easy                   0",
+            "medium                 0",
+            "hard                   2",
+            "fiendish               0",
+            "average guesses     20.0",
+        ]
+    );
+}
+
+#[test]
+fn test_count() {
+    let output = run(&["--count"], &format!("{}\n\n", HARD));
+    assert!(output.contains("(1 solution)"));
+
+    // Five clues, counting them all would never end
+    let few = format!("1.......2.......3.......4.......5{}", ".".repeat(48));
+    let output = run(&["--count", "--max-solutions=50"], &format!("{}\n\n", few));
+    assert!(output.contains("(at least 50 solutions)"));
+    let output = run(&["--count"], &format!("{}\n\n", few));
+    assert!(output.contains("(at least 1000 solutions)"));
+}
+
+#[test]
+fn test_solutions() {
+    let ambiguous = HARD.replacen('4', ".", 1);
+    let output = run(
+        &["--solutions", "--max-solutions", "3"],
+        &format!("{}\n\n", ambiguous),
+    );
+    assert!(output.contains("(at least 3 solutions)"));
+    // The puzzle and three solutions
+    assert_eq!(output.matches("+------+------+------+").count(), 4 * 4);
+
+    let output = run(&["--solutions"], &format!("{}\n\n", HARD));
+    assert!(output.contains("(1 solution)"));
+    assert_eq!(output.matches("+------+------+------+").count(), 2 * 4);
+}
+
+#[test]
+fn test_repl() {
+    let input = format!(
+        "solve\n{}\ncount\nhint\nsolve\nnonsense\nquit\nsolve\n",
+        HARD
+    );
+    let output = run(&["--repl"], &input);
+    assert!(output.starts_with("> type a puzzle first\n"));
+    assert!(output.contains("(1 solution)"));
+    assert!(output.contains("|4 1 7 |3 6 9 |8 2 5 |"));
+    assert!(output.contains("not a command nor a puzzle"));
+    // Nothing is read after quit
+    assert_eq!(output.matches("> ").count(), 7);
+}
+
+#[test]
+fn test_generate() {
+    let output = run(&["--generate", "2", "--difficulty=easy"], "");
+    let puzzles: Vec<Sudoku> = output
+        .lines()
+        .map(|line| Sudoku::try_from(line).unwrap())
+        .collect();
+    assert_eq!(puzzles.len(), 2);
+    for puzzle in puzzles {
+        assert!(puzzle.is_unique());
+        assert_eq!(puzzle.difficulty(), Some(Difficulty::Easy));
+    }
+}
+
+#[test]
+fn test_exit_status() {
+    let unsolvable = HARD.replacen('.', "4", 1);
+    let malformed = &HARD[1..];
+    run(&[], &format!("{}\n\n{}\n\n", HARD, HARD));
+    run_with_status(&[], &format!("{}\n\n{}\n\n", HARD, unsolvable), 1);
+    run_with_status(&["--count"], &format!("{}\n\n", unsolvable), 1);
+    run_with_status(&["--jobs", "1"], &format!("{}\n\n", unsolvable), 1);
+
+    // The other puzzles are still solved
+    let input = format!("{}\n\n{}\n\n{}\n\n", malformed, unsolvable, HARD);
+    let output = run_with_status(&[], &input, 2);
+    assert!(output.contains("|4 1 7 |3 6 9 |8 2 5 |"));
+    run_with_status(&["--strict"], &format!("{}{}\n\n", HARD, HARD), 2);
+
+    run_with_status(&["--jobs", "0"], "", 3);
+    run_with_status(&["--input-format=xml"], "", 3);
+    run_with_status(&["--jsno"], "", 3);
+    run_with_status(&["-x"], "", 3);
+    run(&["--help"], "");
+}
+
+#[test]
+fn test_number() {
+    const ESCARGOT: &str =
+        "1....7.9..3..2...8..96..5....53..9...1..8...26....4...3......1..4......7..7...3..";
+    let input = format!("{}\n\n{}\n\n", ESCARGOT, HARD);
+    let output = run(&["--number"], &input);
+    let first = output.find("Puzzle 1:\n").unwrap();
+    let second = output.find("Puzzle 2:\n").unwrap();
+    assert!(first < second);
+    assert!(output[second..].starts_with("Puzzle 2:\n+------+"));
+    assert!(!run(&[], &input).contains("Puzzle"));
+
+    // Sorted, the easier one comes first but keeps its number
+    let input = format!("{}\n\n{}\n\n", HARD, ESCARGOT);
+    let output = run(&["--number", "--sort-by-difficulty"], &input);
+    assert!(output.find("Puzzle 2:").unwrap() < output.find("Puzzle 1:").unwrap());
+
+    // Malformed puzzles are counted too
+    let input = format!("{}\n\n{}\n\n", &HARD[1..], HARD);
+    let output = run_with_status(&["--number", "--count"], &input, 2);
+    assert!(output.starts_with("Puzzle 2:\n"));
+    assert!(!output.contains("Puzzle 1:"));
+}
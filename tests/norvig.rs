@@ -0,0 +1,56 @@
+//! Solves the bundled puzzle files, in the spirit of the test suite of
+//! Peter Norvig's solver this one is based on
+//!
+//! `puzzles/hard.txt` has the hardest puzzles from his writeup, and
+//! `puzzles/easy.txt` fifty generated puzzles constraint propagation
+//! alone solves.  His own `easy50.txt` and `top95.txt` are not bundled.
+
+use std::convert::TryFrom;
+use std::time::{Duration, Instant};
+
+use sudoku_solver::Sudoku;
+
+/// A lot more than they take even without optimizations, only a
+/// search gone badly wrong gets near it
+const TIME_LIMIT: Duration = Duration::from_secs(10);
+
+/// The puzzles in `file`, one per line
+fn load(file: &str) -> Vec<Sudoku> {
+    let path = format!("{}/puzzles/{}", env!("CARGO_MANIFEST_DIR"), file);
+    let input = std::fs::read_to_string(&path).expect("could not read the puzzles");
+    input
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| Sudoku::try_from(line).expect("malformed puzzle"))
+        .collect()
+}
+
+/// Solves every puzzle, checking the solution keeps its clues, and
+/// returns how many guesses they took altogether
+fn solve_all(puzzles: &[Sudoku]) -> usize {
+    let t0 = Instant::now();
+    let mut guesses = 0;
+    for puzzle in puzzles {
+        let mut solution = puzzle.clone();
+        let stats = solution.solve_stats().expect("unsolvable puzzle");
+        guesses += stats.guesses;
+        assert!(solution.is_solved(), "{} was not solved", puzzle);
+        assert!(puzzle.diff(&solution).iter().all(|&(_, clue, _)| clue == 0));
+    }
+    assert!(t0.elapsed() < TIME_LIMIT, "took {:?}", t0.elapsed());
+    guesses
+}
+
+#[test]
+fn test_easy() {
+    let puzzles = load("easy.txt");
+    assert_eq!(puzzles.len(), 50);
+    assert_eq!(solve_all(&puzzles), 0);
+}
+
+#[test]
+fn test_hard() {
+    let puzzles = load("hard.txt");
+    assert_eq!(puzzles.len(), 11);
+    assert!(solve_all(&puzzles) > 0);
+}